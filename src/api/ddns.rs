@@ -41,7 +41,7 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
 /// - API 請求失敗
 async fn update_ipv4_record() -> Result<impl Responder, AppError> {
     let config = DdnsConfigLoader::load_for_api("ipv4")?;
-    let ddns_service = DdnsService::new(config);
+    let ddns_service = DdnsService::new(config)?;
     let result = ddns_service.update_record().await?;
     
     Ok(HttpResponse::Ok().json(result))
@@ -65,7 +65,7 @@ async fn update_ipv4_record() -> Result<impl Responder, AppError> {
 /// - API 請求失敗
 async fn update_ipv6_record() -> Result<impl Responder, AppError> {
     let config = DdnsConfigLoader::load_for_api("ipv6")?;
-    let ddns_service = DdnsService::new(config);
+    let ddns_service = DdnsService::new(config)?;
     let result = ddns_service.update_record().await?;
     
     Ok(HttpResponse::Ok().json(result))
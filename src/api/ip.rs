@@ -32,11 +32,15 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
 /// {
 ///     "status": "success",
 ///     "data": {
-///         "ip": "xxx.xxx.xxx.xxx"
+///         "ip": "xxx.xxx.xxx.xxx",
+///         "class": "public",
+///         "public": true
 ///     }
 /// }
 /// ```
 /// 
+/// `class` 為地址分類：`public`、`cgnat`、`private`、`loopback`、`link_local` 或 `bogon`
+/// 
 /// 失敗時：
 /// ```json
 /// {
@@ -46,12 +50,17 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
 /// ```
 async fn get_ipv4() -> impl Responder {
     match ip::fetch_ipv4().await {
-        Ok(ip) => HttpResponse::Ok().json(serde_json::json!({
-            "status": "success",
-            "data": {
-                "ip": ip
-            }
-        })),
+        Ok(ip) => {
+            let class = ip::classify(ip.into());
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": "success",
+                "data": {
+                    "ip": ip,
+                    "class": class,
+                    "public": class.is_public()
+                }
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "status": "error",
             "message": e.to_string()
@@ -72,11 +81,15 @@ async fn get_ipv4() -> impl Responder {
 /// {
 ///     "status": "success",
 ///     "data": {
-///         "ip": "xxxx:xxxx:xxxx:xxxx:xxxx:xxxx:xxxx:xxxx"
+///         "ip": "xxxx:xxxx:xxxx:xxxx:xxxx:xxxx:xxxx:xxxx",
+///         "class": "public",
+///         "public": true
 ///     }
 /// }
 /// ```
 /// 
+/// `class` 為地址分類：`public`、`cgnat`、`private`、`loopback`、`link_local` 或 `bogon`
+/// 
 /// 失敗時：
 /// ```json
 /// {
@@ -86,12 +99,17 @@ async fn get_ipv4() -> impl Responder {
 /// ```
 async fn get_ipv6() -> impl Responder {
     match ip::fetch_ipv6().await {
-        Ok(ip) => HttpResponse::Ok().json(serde_json::json!({
-            "status": "success",
-            "data": {
-                "ip": ip
-            }
-        })),
+        Ok(ip) => {
+            let class = ip::classify(ip.into());
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": "success",
+                "data": {
+                    "ip": ip,
+                    "class": class,
+                    "public": class.is_public()
+                }
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "status": "error",
             "message": e.to_string()
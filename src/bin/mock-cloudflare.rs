@@ -0,0 +1,69 @@
+use actix_web::web;
+use cloudflare_ddns::mock::{self, MockCloudflare};
+use log::{info, error};
+use std::env;
+use std::net::SocketAddr;
+
+/// 模擬 Cloudflare API 伺服器入口點
+/// 
+/// 在記憶體中保存 DNS 記錄，將 DDNS 項目的 `api_base_url` 設為 `http://{地址}/client/v4`
+/// 即可在沒有網路的環境下測試
+/// 
+/// # 環境變數
+/// 
+/// - `RUST_LOG`: 日誌級別（默認：info）
+/// - `MOCK_CLOUDFLARE_ADDRESS`: 監聽地址（默認：127.0.0.1:8787）
+/// - `MOCK_CLOUDFLARE_TOKEN`: 要求的 API 令牌或 Global API Key（可選，未設置時接受任何認證）
+/// - `MOCK_CLOUDFLARE_ZONES`: 啟動時建立的區域，以逗號分隔的 `zone_id/name`（可選）
+/// - `MOCK_CLOUDFLARE_RECORDS`: 啟動時建立的記錄，以逗號分隔的 `zone_id/name/type/content`（可選，區域不存在時一併建立）
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let address: SocketAddr = match env::var("MOCK_CLOUDFLARE_ADDRESS")
+        .unwrap_or_else(|_| "127.0.0.1:8787".to_string())
+        .parse()
+    {
+        Ok(address) => address,
+        Err(e) => {
+            error!("Invalid MOCK_CLOUDFLARE_ADDRESS: {}", e);
+            return Ok(());
+        }
+    };
+
+    let mock = web::Data::new(MockCloudflare::new(env::var("MOCK_CLOUDFLARE_TOKEN").ok()));
+    for spec in env::var("MOCK_CLOUDFLARE_ZONES").unwrap_or_default().split(',').filter(|spec| !spec.trim().is_empty()) {
+        match spec.trim().split('/').collect::<Vec<_>>().as_slice() {
+            [zone_id, name] => {
+                mock.add_zone(zone_id, name);
+                info!("Created zone {} with ID {}", name, zone_id);
+            }
+            _ => {
+                error!("Invalid MOCK_CLOUDFLARE_ZONES entry {}, expected zone_id/name", spec);
+                return Ok(());
+            }
+        }
+    }
+    for spec in env::var("MOCK_CLOUDFLARE_RECORDS").unwrap_or_default().split(',').filter(|spec| !spec.trim().is_empty()) {
+        match spec.trim().split('/').collect::<Vec<_>>().as_slice() {
+            [zone_id, name, record_type, content] => {
+                let id = mock.insert(zone_id, name, record_type, content);
+                info!("Created {} record {} -> {} with ID {}", record_type, name, content, id);
+            }
+            _ => {
+                error!("Invalid MOCK_CLOUDFLARE_RECORDS entry {}, expected zone_id/name/type/content", spec);
+                return Ok(());
+            }
+        }
+    }
+
+    let (server, _) = mock::start(mock, address)?;
+    
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => info!("Received termination signal, shutting down mock Cloudflare API..."),
+        Err(err) => error!("Failed to listen for termination signal: {}", err),
+    }
+    server.stop(true).await;
+    
+    Ok(())
+}
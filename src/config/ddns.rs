@@ -2,9 +2,24 @@ use std::env;
 use std::fs;
 use serde_json;
 use crate::error::AppError;
-use crate::services::ddns::DdnsConfig;
+use crate::services::ddns::{DdnsConfig, RecordTarget};
+use crate::services::ip::{self, IpSourceConfig, NonPublicPolicy};
+use crate::services::retry::RetryPolicy;
 use log::{info, warn, error};
 
+/// 從環境變量讀取的 Cloudflare 認證資訊
+/// 
+/// # 欄位
+/// 
+/// - `api_token`: API 令牌（使用 Global API Key 時為空）
+/// - `api_email`: 帳號電子郵件（僅用於 Global API Key）
+/// - `api_key`: Global API Key（可選）
+struct EnvCredentials {
+    api_token: String,
+    api_email: Option<String>,
+    api_key: Option<String>,
+}
+
 /// DDNS 配置加載器
 /// 
 /// 提供統一的配置加載邏輯，支持從環境變量或配置文件加載
@@ -117,17 +132,18 @@ impl DdnsConfigLoader {
     /// 
     /// - `Result<DdnsConfig, AppError>`: 成功時返回 DDNS 配置，失敗時返回錯誤
     fn load_ipv4_config() -> Result<DdnsConfig, AppError> {
-        let api_token = env::var("CLOUDFLARE_API_TOKEN")
-            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_API_TOKEN environment variable".to_string()))?;
+        let credentials = Self::load_credentials("")?.ok_or_else(|| AppError::ConfigError(
+            "Missing CLOUDFLARE_API_TOKEN (or CLOUDFLARE_API_KEY and CLOUDFLARE_API_EMAIL) environment variable".to_string()
+        ))?;
         
         let zone_id = env::var("CLOUDFLARE_ZONE_ID")
             .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_ZONE_ID environment variable".to_string()))?;
         
-        let record_id = env::var("CLOUDFLARE_RECORD_ID")
-            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_RECORD_ID environment variable".to_string()))?;
+        // 記錄 ID 為可選，未設置時依記錄名稱查詢
+        let record_id = env::var("CLOUDFLARE_RECORD_ID").ok().filter(|id| !id.trim().is_empty());
         
-        let record_name = env::var("CLOUDFLARE_RECORD_NAME")
-            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_RECORD_NAME environment variable".to_string()))?;
+        let (record_name, records) = Self::split_record_names(&env::var("CLOUDFLARE_RECORD_NAME")
+            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_RECORD_NAME environment variable".to_string()))?);
         
         let update_interval = env::var("DDNS_UPDATE_INTERVAL")
             .unwrap_or_else(|_| "300".to_string())
@@ -135,12 +151,29 @@ impl DdnsConfigLoader {
             .map_err(|_| AppError::ConfigError("DDNS_UPDATE_INTERVAL must be a number".to_string()))?;
         
         let config = DdnsConfig {
-            api_token,
+            api_token: credentials.api_token,
+            api_email: credentials.api_email,
+            api_key: credentials.api_key,
             zone_id,
+            api_base_url: Self::load_api_base_url(),
             record_id,
             record_name,
+            records,
             update_interval,
             ip_type: "ipv4".to_string(),
+            ip_sources: Self::load_ip_sources()?,
+            ip_quorum: Self::load_ip_quorum()?,
+            watch_address_changes: Self::load_watch_address_changes()?,
+            non_public_policy: Self::load_non_public_policy()?,
+            create_if_missing: Self::load_create_if_missing()?,
+            ttl: Self::load_ttl()?,
+            proxied: Self::load_proxied()?,
+            comment: env::var("DDNS_COMMENT").ok(),
+            tags: Self::load_tags(),
+            reconcile_interval: Self::load_reconcile_interval()?,
+            reconcile_dns: Self::load_reconcile_dns()?,
+            remove_when_unavailable: false,
+            retry: Self::load_retry_policy()?,
         };
         
         Self::validate_config(&config, "IPv4 Configuration")?;
@@ -154,12 +187,11 @@ impl DdnsConfigLoader {
     /// 
     /// - `Result<DdnsConfig, AppError>`: 成功時返回 DDNS 配置，失敗時返回錯誤
     fn load_ipv6_config() -> Result<DdnsConfig, AppError> {
-        // 優先使用專用的 IPv6 API 令牌和區域 ID
-        let api_token = if let Ok(token) = env::var("CLOUDFLARE_API_TOKEN_V6") {
-            token
-        } else {
-            env::var("CLOUDFLARE_API_TOKEN")
-                .map_err(|_| AppError::ConfigError("Missing API token environment variable".to_string()))?
+        // 優先使用專用的 IPv6 認證資訊和區域 ID
+        let credentials = match Self::load_credentials("_V6")? {
+            Some(credentials) => credentials,
+            None => Self::load_credentials("")?
+                .ok_or_else(|| AppError::ConfigError("Missing API token environment variable".to_string()))?,
         };
         
         let zone_id = if let Ok(zone) = env::var("CLOUDFLARE_ZONE_ID_V6") {
@@ -169,12 +201,11 @@ impl DdnsConfigLoader {
                 .map_err(|_| AppError::ConfigError("Missing zone ID environment variable".to_string()))?
         };
         
-        // IPv6 記錄 ID 和名稱是必需的
-        let record_id = env::var("CLOUDFLARE_RECORD_ID_V6")
-            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_RECORD_ID_V6 environment variable".to_string()))?;
+        // IPv6 記錄名稱是必需的，記錄 ID 為可選
+        let record_id = env::var("CLOUDFLARE_RECORD_ID_V6").ok().filter(|id| !id.trim().is_empty());
         
-        let record_name = env::var("CLOUDFLARE_RECORD_NAME_V6")
-            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_RECORD_NAME_V6 environment variable".to_string()))?;
+        let (record_name, records) = Self::split_record_names(&env::var("CLOUDFLARE_RECORD_NAME_V6")
+            .map_err(|_| AppError::ConfigError("Missing CLOUDFLARE_RECORD_NAME_V6 environment variable".to_string()))?);
         
         // 可以使用專用更新間隔或與 IPv4 相同的間隔
         let update_interval = env::var("DDNS_UPDATE_INTERVAL_V6")
@@ -185,12 +216,29 @@ impl DdnsConfigLoader {
             .map_err(|_| AppError::ConfigError("Update interval must be a number".to_string()))?;
         
         let config = DdnsConfig {
-            api_token,
+            api_token: credentials.api_token,
+            api_email: credentials.api_email,
+            api_key: credentials.api_key,
             zone_id,
+            api_base_url: Self::load_api_base_url(),
             record_id,
             record_name,
+            records,
             update_interval,
             ip_type: "ipv6".to_string(),
+            ip_sources: Self::load_ip_sources()?,
+            ip_quorum: Self::load_ip_quorum()?,
+            watch_address_changes: Self::load_watch_address_changes()?,
+            non_public_policy: Self::load_non_public_policy()?,
+            create_if_missing: Self::load_create_if_missing()?,
+            ttl: Self::load_ttl()?,
+            proxied: Self::load_proxied()?,
+            comment: env::var("DDNS_COMMENT").ok(),
+            tags: Self::load_tags(),
+            reconcile_interval: Self::load_reconcile_interval()?,
+            reconcile_dns: Self::load_reconcile_dns()?,
+            remove_when_unavailable: false,
+            retry: Self::load_retry_policy()?,
         };
         
         Self::validate_config(&config, "IPv6 Configuration")?;
@@ -198,6 +246,241 @@ impl DdnsConfigLoader {
         Ok(config)
     }
     
+    /// 拆分以逗號分隔的記錄名稱
+    /// 
+    /// 第一個名稱作為 `record_name`（與記錄 ID 環境變量對應），其餘作為 `records`
+    /// 
+    /// # 參數
+    /// 
+    /// - `value`: 記錄名稱，例如 `home.example.com,vpn.example.com`
+    fn split_record_names(value: &str) -> (String, Vec<RecordTarget>) {
+        let mut names = value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from);
+        
+        let record_name = names.next().unwrap_or_default();
+        let records = names.map(|name| RecordTarget { name: Some(name), id: None }).collect();
+        
+        (record_name, records)
+    }
+    
+    /// 從環境變量加載 Cloudflare 認證資訊
+    /// 
+    /// `CLOUDFLARE_API_TOKEN{suffix}` 優先；未設置時使用 `CLOUDFLARE_API_KEY{suffix}` 與
+    /// `CLOUDFLARE_API_EMAIL{suffix}`（IPv6 未設置專用電子郵件時使用 `CLOUDFLARE_API_EMAIL`）
+    /// 
+    /// # 參數
+    /// 
+    /// - `suffix`: 環境變量後綴（IPv4 為空字串，IPv6 為 `_V6`）
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<EnvCredentials>, AppError>`: 未設置令牌與 Global API Key 時返回 `None`，
+    ///   Global API Key 缺少電子郵件時返回錯誤
+    fn load_credentials(suffix: &str) -> Result<Option<EnvCredentials>, AppError> {
+        let var = |name: &str| env::var(format!("{}{}", name, suffix)).ok().filter(|value| !value.trim().is_empty());
+        
+        if let Some(api_token) = var("CLOUDFLARE_API_TOKEN") {
+            return Ok(Some(EnvCredentials { api_token, api_email: None, api_key: None }));
+        }
+        let Some(api_key) = var("CLOUDFLARE_API_KEY") else {
+            return Ok(None);
+        };
+        
+        let api_email = var("CLOUDFLARE_API_EMAIL")
+            .or_else(|| env::var("CLOUDFLARE_API_EMAIL").ok().filter(|value| !value.trim().is_empty()))
+            .ok_or_else(|| AppError::ConfigError(format!("CLOUDFLARE_API_KEY{} requires CLOUDFLARE_API_EMAIL", suffix)))?;
+        
+        Ok(Some(EnvCredentials { api_token: String::new(), api_email: Some(api_email), api_key: Some(api_key) }))
+    }
+    
+    /// 從環境變量加載 Cloudflare API 基礎 URL
+    /// 
+    /// # 返回
+    /// 
+    /// - `Option<String>`: `CLOUDFLARE_API_BASE_URL` 未設置或為空時返回 `None`
+    fn load_api_base_url() -> Option<String> {
+        env::var("CLOUDFLARE_API_BASE_URL").ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+    }
+    
+    /// 從環境變量加載 IP 來源備援鏈
+    /// 
+    /// `DDNS_IP_SOURCES` 為以逗號分隔的來源名稱或 URL，例如 `icanhazip,cloudflare`
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<Vec<IpSourceConfig>>, AppError>`: 未設置時返回 `None`，解析失敗時返回錯誤
+    fn load_ip_sources() -> Result<Option<Vec<IpSourceConfig>>, AppError> {
+        let Ok(value) = env::var("DDNS_IP_SOURCES") else {
+            return Ok(None);
+        };
+        
+        let sources = value
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(IpSourceConfig::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(Some(sources))
+    }
+    
+    /// 從環境變量加載共識模式所需的一致來源數
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<usize>, AppError>`: 未設置 `DDNS_IP_QUORUM` 時返回 `None`，解析失敗時返回錯誤
+    fn load_ip_quorum() -> Result<Option<usize>, AppError> {
+        env::var("DDNS_IP_QUORUM")
+            .ok()
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_IP_QUORUM must be a number".to_string())))
+            .transpose()
+    }
+    
+    /// 從環境變量加載是否監聽本機地址變更
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<bool, AppError>`: 未設置 `DDNS_WATCH_ADDRESS_CHANGES` 時返回 `false`，解析失敗時返回錯誤
+    fn load_watch_address_changes() -> Result<bool, AppError> {
+        env::var("DDNS_WATCH_ADDRESS_CHANGES")
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_WATCH_ADDRESS_CHANGES must be true or false".to_string())))
+            .unwrap_or(Ok(false))
+    }
+    
+    /// 從環境變量加載非公網地址的處理策略
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<NonPublicPolicy>, AppError>`: 未設置 `DDNS_NON_PUBLIC_POLICY` 時返回 `None`，解析失敗時返回錯誤
+    fn load_non_public_policy() -> Result<Option<NonPublicPolicy>, AppError> {
+        env::var("DDNS_NON_PUBLIC_POLICY")
+            .ok()
+            .map(|value| NonPublicPolicy::parse(&value)
+                .ok_or_else(|| AppError::ConfigError("DDNS_NON_PUBLIC_POLICY must be warn, skip or fail".to_string())))
+            .transpose()
+    }
+    
+    /// 從環境變量加載找不到記錄時是否自動建立
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<bool, AppError>`: 未設置 `DDNS_CREATE_IF_MISSING` 時返回 `false`，解析失敗時返回錯誤
+    fn load_create_if_missing() -> Result<bool, AppError> {
+        env::var("DDNS_CREATE_IF_MISSING")
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_CREATE_IF_MISSING must be true or false".to_string())))
+            .unwrap_or(Ok(false))
+    }
+    
+    /// 從環境變量加載記錄 TTL
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<u32>, AppError>`: 未設置 `DDNS_TTL` 時返回 `None`，解析失敗時返回錯誤
+    fn load_ttl() -> Result<Option<u32>, AppError> {
+        env::var("DDNS_TTL")
+            .ok()
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_TTL must be a number".to_string())))
+            .transpose()
+    }
+    
+    /// 從環境變量加載是否啟用 Cloudflare 代理
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<bool>, AppError>`: 未設置 `DDNS_PROXIED` 時返回 `None`，解析失敗時返回錯誤
+    fn load_proxied() -> Result<Option<bool>, AppError> {
+        env::var("DDNS_PROXIED")
+            .ok()
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_PROXIED must be true or false".to_string())))
+            .transpose()
+    }
+    
+    /// 從環境變量加載記錄標籤
+    /// 
+    /// `DDNS_TAGS` 為以逗號分隔的標籤，例如 `env:prod,owner:ddns`；設置為空字串時清除所有標籤
+    /// 
+    /// # 返回
+    /// 
+    /// - `Option<Vec<String>>`: 未設置時返回 `None`
+    fn load_tags() -> Option<Vec<String>> {
+        env::var("DDNS_TAGS").ok().map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect()
+        })
+    }
+    
+    /// 從環境變量加載與 Cloudflare 記錄核對的間隔
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<u64>, AppError>`: 未設置 `DDNS_RECONCILE_INTERVAL` 時返回 `None`，解析失敗時返回錯誤
+    fn load_reconcile_interval() -> Result<Option<u64>, AppError> {
+        env::var("DDNS_RECONCILE_INTERVAL")
+            .ok()
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_RECONCILE_INTERVAL must be a number".to_string())))
+            .transpose()
+    }
+    
+    /// 從環境變量加載核對時是否透過 DNS 解析記錄名稱
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<bool, AppError>`: 未設置 `DDNS_RECONCILE_DNS` 時返回 `false`，解析失敗時返回錯誤
+    fn load_reconcile_dns() -> Result<bool, AppError> {
+        env::var("DDNS_RECONCILE_DNS")
+            .map(|value| value.parse()
+                .map_err(|_| AppError::ConfigError("DDNS_RECONCILE_DNS must be true or false".to_string())))
+            .unwrap_or(Ok(false))
+    }
+    
+    /// 從環境變量加載重試策略
+    /// 
+    /// 未設置的欄位使用預設值
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<RetryPolicy, AppError>`: 成功時返回重試策略，解析失敗時返回錯誤
+    fn load_retry_policy() -> Result<RetryPolicy, AppError> {
+        fn seconds(name: &str) -> Result<Option<u64>, AppError> {
+            env::var(name)
+                .ok()
+                .map(|value| value.parse()
+                    .map_err(|_| AppError::ConfigError(format!("{} must be a number", name))))
+                .transpose()
+        }
+        
+        let mut policy = RetryPolicy::default();
+        if let Some(delay) = seconds("DDNS_RETRY_INITIAL_DELAY")? {
+            policy.initial_delay = delay;
+        }
+        if let Some(delay) = seconds("DDNS_RETRY_MAX_DELAY")? {
+            policy.max_delay = delay;
+        }
+        if let Some(retries) = seconds("DDNS_RETRY_MAX_RETRIES")? {
+            policy.max_retries = u32::try_from(retries)
+                .map_err(|_| AppError::ConfigError("DDNS_RETRY_MAX_RETRIES is too large".to_string()))?;
+        }
+        if let Some(delay) = seconds("DDNS_RETRY_PERSISTENT_DELAY")? {
+            policy.persistent_delay = delay;
+        }
+        
+        Ok(policy)
+    }
+    
     /// 驗證 DDNS 配置
     /// 
     /// # 參數
@@ -209,35 +492,72 @@ impl DdnsConfigLoader {
     /// 
     /// - `Result<(), AppError>`: 成功時返回 ()，失敗時返回錯誤
     fn validate_config(config: &DdnsConfig, context: &str) -> Result<(), AppError> {
-        // 驗證 API 令牌
-        if config.api_token.trim().is_empty() {
-            return Err(AppError::ConfigError(format!("{}: API token cannot be empty", context)));
-        }
+        // 驗證認證資訊（API 令牌或 Global API Key 與電子郵件）
+        config.credentials()
+            .map_err(|e| AppError::ConfigError(format!("{}: {}", context, e)))?;
         
         // 驗證區域 ID
         if config.zone_id.trim().is_empty() {
             return Err(AppError::ConfigError(format!("{}: Zone ID cannot be empty", context)));
         }
         
-        // 驗證記錄 ID
-        if config.record_id.trim().is_empty() {
+        // 驗證 API 基礎 URL
+        if config.api_base_url.as_ref().is_some_and(|url| !url.starts_with("http://") && !url.starts_with("https://")) {
+            return Err(AppError::ConfigError(format!("{}: API base URL must start with http:// or https://", context)));
+        }
+        
+        // 驗證記錄 ID（設置時不可為空）
+        if config.record_id.as_ref().is_some_and(|id| id.trim().is_empty()) {
             return Err(AppError::ConfigError(format!("{}: Record ID cannot be empty", context)));
         }
         
-        // 驗證記錄名稱
-        if config.record_name.trim().is_empty() {
+        // 驗證記錄名稱（至少一筆記錄，每筆記錄需有名稱或 ID）
+        let targets = config.targets();
+        if targets.is_empty() {
             return Err(AppError::ConfigError(format!("{}: Record name cannot be empty", context)));
         }
+        for (i, target) in targets.iter().enumerate() {
+            let name = target.name.as_deref().map(str::trim);
+            let id = target.id.as_deref().map(str::trim);
+            if name.is_some_and(str::is_empty) || id.is_some_and(str::is_empty) || (name.is_none() && id.is_none()) {
+                return Err(AppError::ConfigError(format!("{}: Record {} needs a non-empty name or ID", context, i)));
+            }
+            if name.is_some() && targets[..i].iter().any(|other| other.name.as_deref().map(str::trim) == name) {
+                return Err(AppError::ConfigError(format!("{}: Record {} is listed more than once", context, name.unwrap_or_default())));
+            }
+        }
         
         // 驗證更新間隔
         if config.update_interval < 5 {
             return Err(AppError::ConfigError(format!("{}: Update interval cannot be less than 5 seconds", context)));
         }
         
+        // 驗證核對間隔
+        if config.reconcile_interval.is_some_and(|interval| interval < 60) {
+            return Err(AppError::ConfigError(format!("{}: Reconcile interval cannot be less than 60 seconds", context)));
+        }
+        
+        // 驗證重試策略
+        config.retry.validate()
+            .map_err(|e| AppError::ConfigError(format!("{}: {}", context, e)))?;
+        
+        // 驗證 TTL（1 表示自動）
+        if config.ttl.is_some_and(|ttl| ttl != 1 && !(60..=86400).contains(&ttl)) {
+            return Err(AppError::ConfigError(format!("{}: TTL must be 1 (automatic) or between 60 and 86400 seconds", context)));
+        }
+        
+        // 驗證 IP 來源及共識設置
+        if config.ip_sources.as_ref().is_some_and(|sources| sources.is_empty()) {
+            return Err(AppError::ConfigError(format!("{}: IP sources cannot be empty", context)));
+        }
+        let sources = config.ip_sources.clone().unwrap_or_else(ip::default_sources);
+        ip::build_detector(&sources, config.ip_quorum.or(ip::settings().quorum))
+            .map_err(|e| AppError::ConfigError(format!("{}: {}", context, e)))?;
+        
         // 驗證 IP 類型
         match config.ip_type.as_str() {
-            "ipv4" | "ipv6" => Ok(()),
-            _ => Err(AppError::ConfigError(format!("{}: IP type must be ipv4 or ipv6", context)))
+            "ipv4" | "ipv6" | "both" | "auto" => Ok(()),
+            _ => Err(AppError::ConfigError(format!("{}: IP type must be ipv4, ipv6, both or auto", context)))
         }
     }
 } 
\ No newline at end of file
@@ -1,6 +1,9 @@
-use config::{Config, ConfigError, File};
+use config::{Config, ConfigError, File, FileFormat};
 use serde::Deserialize;
 use std::env;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use crate::services::ip::{self, IpSourceConfig, NonPublicPolicy};
 
 /// 伺服器設置結構
 /// 
@@ -14,14 +17,119 @@ pub struct ServerSettings {
     pub port: u16,
 }
 
+/// IP 偵測設置結構
+/// 
+/// # 欄位
+/// 
+/// - `sources`: 依優先順序排列的 IP 來源，依序嘗試直到成功
+/// - `quorum`: 共識模式所需的一致來源數（可選，設置時同時查詢所有來源）
+/// - `non_public_policy`: 偵測到 CGNAT、私有或 bogon 地址時的處理策略
+/// - `local_ipv4`: IPv4 查詢綁定的本機地址（可選，未設置時綁定任意 IPv4 地址）
+/// - `local_ipv6`: IPv6 查詢綁定的本機地址（可選，未設置時綁定任意 IPv6 地址）
+/// - `cache_ttl`: 查詢結果在所有記錄與 API 請求間共用的快取時間（秒）
+#[derive(Debug, Clone, Deserialize)]
+pub struct IpSettings {
+    #[serde(default = "ip::builtin_sources")]
+    pub sources: Vec<IpSourceConfig>,
+    #[serde(default)]
+    pub quorum: Option<usize>,
+    #[serde(default)]
+    pub non_public_policy: NonPublicPolicy,
+    #[serde(default)]
+    pub local_ipv4: Option<Ipv4Addr>,
+    #[serde(default)]
+    pub local_ipv6: Option<Ipv6Addr>,
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+}
+
+/// IP 查詢結果的預設快取時間（秒）
+fn default_cache_ttl() -> u64 {
+    30
+}
+
+impl Default for IpSettings {
+    fn default() -> Self {
+        Self {
+            sources: ip::builtin_sources(),
+            quorum: None,
+            non_public_policy: NonPublicPolicy::default(),
+            local_ipv4: None,
+            local_ipv6: None,
+            cache_ttl: default_cache_ttl(),
+        }
+    }
+}
+
+/// 出站 HTTP 客戶端設置結構
+/// 
+/// 同時用於 IP 查詢與 Cloudflare API 請求
+/// 
+/// # 欄位
+/// 
+/// - `connect_timeout`: 建立連線的逾時（秒）
+/// - `timeout`: 整個請求（含讀取響應）的逾時（秒）
+/// - `proxy`: HTTP 或 SOCKS5 代理（例如 `http://proxy:3128`、`socks5://127.0.0.1:1080`，可選）
+/// - `user_agent`: User-Agent 標頭
+/// - `local_address`: 出站連線綁定的本機地址（可選）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub connect_timeout: u64,
+    pub timeout: u64,
+    pub proxy: Option<String>,
+    pub user_agent: String,
+    pub local_address: Option<IpAddr>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout: 10,
+            timeout: 30,
+            proxy: None,
+            user_agent: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string(),
+            local_address: None,
+        }
+    }
+}
+
+/// 狀態存儲設置結構
+/// 
+/// # 欄位
+/// 
+/// - `data_dir`: 保存 DDNS 狀態文件（`state.json`）的目錄
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StateSettings {
+    pub data_dir: PathBuf,
+}
+
+impl Default for StateSettings {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+        }
+    }
+}
+
 /// 應用程式設置結構
 /// 
 /// # 欄位
 /// 
 /// - `server`: 伺服器相關設置
+/// - `ip`: IP 偵測相關設置
+/// - `http`: 出站 HTTP 客戶端設置
+/// - `state`: 狀態存儲設置
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub server: ServerSettings,
+    #[serde(default)]
+    pub ip: IpSettings,
+    #[serde(default)]
+    pub http: HttpSettings,
+    #[serde(default)]
+    pub state: StateSettings,
 }
 
 impl Settings {
@@ -43,8 +151,8 @@ impl Settings {
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
         
         let s = Config::builder()
-            .add_source(File::with_name("config/default").required(true))
-            .add_source(File::with_name(&format!("config/{}", run_mode)).required(false))
+            .add_source(File::with_name("config/default").format(FileFormat::Toml).required(true))
+            .add_source(File::with_name(&format!("config/{}", run_mode)).format(FileFormat::Toml).required(false))
             .build()?;
             
         s.try_deserialize()
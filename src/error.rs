@@ -8,8 +8,15 @@ use serde_json::json;
 /// 
 /// - `ConfigError`: 配置相關錯誤
 /// - `ExternalServiceError`: 外部服務錯誤
+/// - `InvalidIpResponse`: IP 查詢服務返回無效的地址
+/// - `NonPublicAddress`: 偵測到的地址不可公開路由（CGNAT、私有或 bogon）
+/// - `AddressFamilyUnavailable`: 主機沒有所要求版本（IPv4 / IPv6）的地址或路由
+/// - `AuthenticationError`: Cloudflare 拒絕認證（令牌無效、過期或權限不足）
+/// - `NotFound`: Cloudflare 上找不到區域或記錄
+/// - `ValidationError`: Cloudflare 拒絕請求內容（記錄內容、TTL 等無效）
+/// - `RateLimited`: Cloudflare 限制了請求頻率
 /// - `InternalError`: 內部服務器錯誤
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum AppError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
@@ -17,6 +24,27 @@ pub enum AppError {
     #[error("External service error: {0}")]
     ExternalServiceError(String),
     
+    #[error("Invalid IP response: {0}")]
+    InvalidIpResponse(String),
+    
+    #[error("Non-public address: {0}")]
+    NonPublicAddress(String),
+    
+    #[error("Address family unavailable: {0}")]
+    AddressFamilyUnavailable(String),
+    
+    #[error("Authentication error: {0}")]
+    AuthenticationError(String),
+    
+    #[error("Not found: {0}")]
+    NotFound(String),
+    
+    #[error("Validation error: {0}")]
+    ValidationError(String),
+    
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+    
     #[error("Internal server error: {0}")]
     InternalError(String),
 }
@@ -31,6 +59,13 @@ pub enum AppError {
 /// 
 /// - `ConfigError`: 500 Internal Server Error
 /// - `ExternalServiceError`: 503 Service Unavailable
+/// - `InvalidIpResponse`: 502 Bad Gateway
+/// - `NonPublicAddress`: 422 Unprocessable Entity
+/// - `AddressFamilyUnavailable`: 503 Service Unavailable
+/// - `AuthenticationError`: 502 Bad Gateway
+/// - `NotFound`: 404 Not Found
+/// - `ValidationError`: 422 Unprocessable Entity
+/// - `RateLimited`: 429 Too Many Requests
 /// - `InternalError`: 500 Internal Server Error
 impl ResponseError for AppError {
     fn error_response(&self) -> HttpResponse {
@@ -39,6 +74,20 @@ impl ResponseError for AppError {
                 .json(json!({"status": "error", "message": msg})),
             AppError::ExternalServiceError(msg) => HttpResponse::ServiceUnavailable()
                 .json(json!({"status": "error", "message": msg})),
+            AppError::InvalidIpResponse(msg) => HttpResponse::BadGateway()
+                .json(json!({"status": "error", "message": msg})),
+            AppError::NonPublicAddress(msg) => HttpResponse::UnprocessableEntity()
+                .json(json!({"status": "error", "message": msg})),
+            AppError::AddressFamilyUnavailable(msg) => HttpResponse::ServiceUnavailable()
+                .json(json!({"status": "error", "message": msg})),
+            AppError::AuthenticationError(msg) => HttpResponse::BadGateway()
+                .json(json!({"status": "error", "message": msg})),
+            AppError::NotFound(msg) => HttpResponse::NotFound()
+                .json(json!({"status": "error", "message": msg})),
+            AppError::ValidationError(msg) => HttpResponse::UnprocessableEntity()
+                .json(json!({"status": "error", "message": msg})),
+            AppError::RateLimited(msg) => HttpResponse::TooManyRequests()
+                .json(json!({"status": "error", "message": msg})),
             AppError::InternalError(msg) => HttpResponse::InternalServerError()
                 .json(json!({"status": "error", "message": msg})),
         }
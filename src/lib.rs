@@ -2,6 +2,8 @@ pub mod api;
 pub mod services;
 pub mod config;
 pub mod error;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 
 use actix_web::{App, HttpServer, middleware::Logger};
 use std::io;
@@ -1,10 +1,18 @@
 use cloudflare_ddns::config::Settings;
+use cloudflare_ddns::config::settings::StateSettings;
 use cloudflare_ddns::config::DdnsConfigLoader;
 use cloudflare_ddns::run_server;
 use cloudflare_ddns::services::ddns::DdnsService;
-use log::{info, error};
+use cloudflare_ddns::services::{http_client, ip};
+use cloudflare_ddns::services::state::StateStore;
+use log::{info, warn, error};
 use std::env;
 use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 相鄰 DDNS 服務啟動的間隔，避免所有服務同時向 Cloudflare 發送請求
+const STARTUP_STAGGER: Duration = Duration::from_secs(2);
 
 /// 啟動 DDNS 服務（作為獨立進程）
 fn start_ddns_service() {
@@ -37,8 +45,9 @@ fn start_ddns_service() {
 /// 
 /// - `RUST_LOG`: 日誌級別（默認：info）
 /// - `CLOUDFLARE_API_TOKEN`: Cloudflare API 令牌
+/// - `CLOUDFLARE_API_KEY` / `CLOUDFLARE_API_EMAIL`: Cloudflare Global API Key 與帳號電子郵件（取代 API 令牌）
 /// - `CLOUDFLARE_ZONE_ID`: Cloudflare 區域 ID
-/// - `CLOUDFLARE_RECORD_ID`: IPv4 DNS 記錄 ID
+/// - `CLOUDFLARE_RECORD_ID`: IPv4 DNS 記錄 ID（可選）
 /// - `CLOUDFLARE_RECORD_NAME`: IPv4 DNS 記錄名稱
 /// - `CLOUDFLARE_RECORD_ID_V6`: IPv6 DNS 記錄 ID（可選）
 /// - `CLOUDFLARE_RECORD_NAME_V6`: IPv6 DNS 記錄名稱（可選）
@@ -61,7 +70,21 @@ async fn main() -> std::io::Result<()> {
     
     if run_mode == "ddns" {
         // 在 DDNS 模式下運行
-        return run_ddns_service().await;
+        let state_settings = match Settings::new() {
+            Ok(settings) => {
+                ip::configure(settings.ip);
+                if let Err(e) = http_client::configure(settings.http) {
+                    error!("Invalid HTTP client settings: {}", e);
+                    return Ok(());
+                }
+                settings.state
+            }
+            Err(e) => {
+                warn!("Failed to load settings, using default IP sources: {}", e);
+                StateSettings::default()
+            }
+        };
+        return run_ddns_service(state_settings).await;
     } else {
         // 在 Web 模式下運行
         // 先啟動 DDNS 服務作為獨立進程
@@ -69,6 +92,8 @@ async fn main() -> std::io::Result<()> {
         
         // 載入設置
         let settings = Settings::new().expect("Failed to load settings");
+        ip::configure(settings.ip);
+        http_client::configure(settings.http).expect("Invalid HTTP client settings");
         
         // 運行 Web 伺服器
         info!("Starting Web server at {}:{}", settings.server.host, settings.server.port);
@@ -79,7 +104,11 @@ async fn main() -> std::io::Result<()> {
 }
 
 /// 運行 DDNS 服務
-async fn run_ddns_service() -> std::io::Result<()> {
+/// 
+/// # 參數
+/// 
+/// - `state_settings`: 狀態存儲設置
+async fn run_ddns_service(state_settings: StateSettings) -> std::io::Result<()> {
     info!("Starting DDNS service...");
     
     // 載入配置
@@ -98,16 +127,36 @@ async fn run_ddns_service() -> std::io::Result<()> {
     
     info!("Successfully loaded {} DDNS configurations", configs.len());
     
+    // 載入上次執行的狀態，讓未變更的記錄在重新啟動後不必重新寫入
+    let state = match StateStore::load(&state_settings.data_dir) {
+        Ok(state) => {
+            info!("Loaded state for {} records from {}", state.len(), state.path().display());
+            state
+        }
+        Err(e) => {
+            warn!("{}, starting with empty state", e);
+            StateStore::empty(&state_settings.data_dir)
+        }
+    };
+    let state = Arc::new(state);
+    
     let mut tasks = Vec::new();
     
-    // 啟動所有配置的 DDNS 服務
-    for ddns_config in configs {
-        let ip_type = ddns_config.ip_type.clone();
-        info!("Starting {} DDNS update service", ip_type);
-        
+    let services = configs.into_iter().filter_map(|config| match DdnsService::for_config(config) {
+        Ok(services) => Some(services),
+        Err(e) => {
+            error!("Skipping DDNS configuration: {}", e);
+            None
+        }
+    });
+    
+    // 啟動所有配置的 DDNS 服務（雙棧項目每個地址族各一個服務），依序錯開啟動時間
+    for (index, ddns_service) in services.flatten().enumerate() {
         // 啟動 DDNS 自動更新任務
-        let ddns_service = DdnsService::new(ddns_config);
+        let ddns_service = ddns_service.with_state(state.clone());
+        let delay = STARTUP_STAGGER * index as u32;
         let handle = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
             ddns_service.start_auto_update().await;
         });
         tasks.push(handle);
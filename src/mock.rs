@@ -0,0 +1,612 @@
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::dev::ServerHandle;
+use actix_web::http::StatusCode;
+use actix_web::http::header::AUTHORIZATION;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use log::{debug, info};
+
+/// 模擬的 Cloudflare DNS 記錄
+///
+/// # 欄位
+///
+/// - `id`: 記錄 ID
+/// - `zone_id`: 所屬區域 ID
+/// - `name`: 記錄名稱
+/// - `record_type`: 記錄類型（A 或 AAAA）
+/// - `content`: 記錄內容（IP 地址）
+/// - `ttl`: 記錄 TTL（秒，1 表示自動）
+/// - `proxied`: 是否啟用 Cloudflare 代理
+/// - `comment`: 記錄備註（可選）
+/// - `tags`: 記錄標籤
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockRecord {
+    pub id: String,
+    pub zone_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: u32,
+    pub proxied: bool,
+    pub comment: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// 模擬的 Cloudflare 區域
+///
+/// # 欄位
+///
+/// - `id`: 區域 ID
+/// - `name`: 區域名稱（例如 `example.com`）
+/// - `status`: 區域狀態，固定為 `active`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockZone {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+}
+
+/// 建立或取代記錄的請求內容
+#[derive(Deserialize)]
+struct RecordBody {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+    #[serde(default = "default_ttl")]
+    ttl: u32,
+    #[serde(default)]
+    proxied: bool,
+    #[serde(default)]
+    comment: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// 部分更新記錄的請求內容
+#[derive(Deserialize)]
+struct RecordPatchBody {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    record_type: Option<String>,
+    content: Option<String>,
+    ttl: Option<u32>,
+    proxied: Option<bool>,
+    comment: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+/// 列出區域的查詢參數
+#[derive(Deserialize)]
+struct ZoneQuery {
+    name: Option<String>,
+}
+
+/// 列出記錄的查詢參數
+#[derive(Deserialize)]
+struct ListQuery {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    record_type: Option<String>,
+}
+
+/// 未指定 TTL 時使用自動 TTL
+fn default_ttl() -> u32 {
+    1
+}
+
+/// 記憶體中的模擬 Cloudflare API
+///
+/// 實作 `zones` 的列出與讀取端點，以及 `zones/{zone_id}/dns_records` 的列出、讀取、建立、取代、部分更新與刪除端點，
+/// 響應格式與錯誤代碼與 Cloudflare API 相同，用於在沒有網路的環境下測試 DDNS 服務。
+/// 只有已建立的區域可以使用，其他區域 ID 返回 Cloudflare 的 7003 錯誤
+///
+/// # 欄位
+///
+/// - `api_token`: 要求的 API 令牌或 Global API Key（可選，未設置時接受任何認證）
+/// - `zones`: 以區域 ID 索引的區域
+/// - `records`: 以記錄 ID 索引的記錄
+/// - `next_id`: 下一個記錄 ID 的序號
+/// - `requests`: 收到的請求（`方法 路徑`），依時間排序
+pub struct MockCloudflare {
+    api_token: Option<String>,
+    zones: Mutex<BTreeMap<String, MockZone>>,
+    records: Mutex<BTreeMap<String, MockRecord>>,
+    next_id: AtomicU64,
+    requests: Mutex<Vec<String>>,
+}
+
+impl MockCloudflare {
+    /// 創建沒有任何區域與記錄的模擬 API
+    ///
+    /// # 參數
+    ///
+    /// - `api_token`: 要求的 API 令牌（`Authorization: Bearer`）或 Global API Key（`X-Auth-Key`，需同時提供 `X-Auth-Email`），
+    ///   `None` 時接受任何認證
+    pub fn new(api_token: Option<String>) -> Self {
+        Self {
+            api_token,
+            zones: Mutex::new(BTreeMap::new()),
+            records: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 新增區域，區域已存在時不做任何事
+    ///
+    /// # 參數
+    ///
+    /// - `zone_id`: 區域 ID
+    /// - `name`: 區域名稱
+    pub fn add_zone(&self, zone_id: &str, name: &str) {
+        if let Entry::Vacant(entry) = self.zones.lock().unwrap_or_else(|e| e.into_inner()).entry(zone_id.to_string()) {
+            entry.insert(MockZone {
+                id: zone_id.to_string(),
+                name: name.to_string(),
+                status: "active".to_string(),
+            });
+        }
+    }
+
+    /// 新增記錄
+    ///
+    /// 區域不存在時一併建立，區域名稱取記錄名稱的最後兩段
+    ///
+    /// # 參數
+    ///
+    /// - `zone_id`: 區域 ID
+    /// - `name`: 記錄名稱
+    /// - `record_type`: 記錄類型（A 或 AAAA）
+    /// - `content`: 記錄內容
+    ///
+    /// # 返回
+    ///
+    /// - `String`: 新記錄的 ID
+    pub fn insert(&self, zone_id: &str, name: &str, record_type: &str, content: &str) -> String {
+        let record = MockRecord {
+            id: self.generate_id(),
+            zone_id: zone_id.to_string(),
+            name: name.to_string(),
+            record_type: record_type.to_string(),
+            content: content.to_string(),
+            ttl: default_ttl(),
+            proxied: false,
+            comment: None,
+            tags: Vec::new(),
+        };
+        let labels: Vec<&str> = name.rsplitn(3, '.').collect();
+        let zone_name = labels.iter().take(2).rev().copied().collect::<Vec<_>>().join(".");
+        self.add_zone(zone_id, &zone_name);
+
+        let id = record.id.clone();
+        self.lock().insert(id.clone(), record);
+        id
+    }
+
+    /// 所有區域，依 ID 排序
+    pub fn zones(&self) -> Vec<MockZone> {
+        self.zones.lock().unwrap_or_else(|e| e.into_inner()).values().cloned().collect()
+    }
+
+    /// 取得記錄
+    ///
+    /// # 參數
+    ///
+    /// - `id`: 記錄 ID
+    pub fn get(&self, id: &str) -> Option<MockRecord> {
+        self.lock().get(id).cloned()
+    }
+
+    /// 所有記錄，依 ID 排序
+    pub fn records(&self) -> Vec<MockRecord> {
+        self.lock().values().cloned().collect()
+    }
+
+    /// 收到的請求，格式為 `方法 路徑`（例如 `PATCH /client/v4/zones/z/dns_records/1`）
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, MockRecord>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn generate_id(&self) -> String {
+        format!("{:032x}", self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// 記錄請求並檢查認證標頭
+    fn authorize(&self, req: &HttpRequest) -> Result<(), Box<HttpResponse>> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).push(format!("{} {}", req.method(), req.path()));
+
+        let header = |name| req.headers().get(name).and_then(|value| value.to_str().ok());
+        let token = header(AUTHORIZATION.as_str()).and_then(|value| value.strip_prefix("Bearer "));
+
+        match (token, header("x-auth-key"), header("x-auth-email"), &self.api_token) {
+            (Some(token), _, _, Some(expected)) if token != expected => {
+                reject(StatusCode::FORBIDDEN, 9109, "Invalid access token")
+            }
+            (Some(_), _, _, _) => Ok(()),
+            (None, Some(_), None, _) => reject(StatusCode::BAD_REQUEST, 9106, "Missing X-Auth-Email header"),
+            (None, Some(key), Some(_), Some(expected)) if key != expected => {
+                reject(StatusCode::FORBIDDEN, 9103, "Unknown X-Auth-Key or X-Auth-Email")
+            }
+            (None, Some(_), Some(_), _) => Ok(()),
+            (None, None, _, _) => reject(StatusCode::BAD_REQUEST, 9106, "Missing X-Auth-Key or Authorization header"),
+        }
+    }
+
+    /// 記錄請求、檢查認證標頭，並確認區域存在
+    fn authorize_zone(&self, req: &HttpRequest, zone_id: &str) -> Result<(), Box<HttpResponse>> {
+        self.authorize(req)?;
+        if self.zones.lock().unwrap_or_else(|e| e.into_inner()).contains_key(zone_id) {
+            Ok(())
+        } else {
+            Err(Box::new(zone_not_found(req)))
+        }
+    }
+}
+
+/// 配置模擬 Cloudflare API 的路由
+///
+/// 需要以 `app_data` 提供 `web::Data<MockCloudflare>`
+///
+/// # 端點
+///
+/// - GET /client/v4/zones: 列出區域（可依 `name` 篩選）
+/// - GET /client/v4/zones/{zone_id}: 讀取區域
+/// - GET /client/v4/zones/{zone_id}/dns_records: 列出記錄（可依 `name`、`type` 篩選）
+/// - POST /client/v4/zones/{zone_id}/dns_records: 建立記錄
+/// - GET /client/v4/zones/{zone_id}/dns_records/{id}: 讀取記錄
+/// - PUT /client/v4/zones/{zone_id}/dns_records/{id}: 取代記錄
+/// - PATCH /client/v4/zones/{zone_id}/dns_records/{id}: 部分更新記錄
+/// - DELETE /client/v4/zones/{zone_id}/dns_records/{id}: 刪除記錄
+///
+/// # 參數
+///
+/// - `cfg`: Web 服務配置
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/client/v4/zones")
+            .route("", web::get().to(list_zones))
+            .route("/{zone_id}", web::get().to(get_zone))
+            .service(
+                web::scope("/{zone_id}/dns_records")
+                    .route("", web::get().to(list_records))
+                    .route("", web::post().to(create_record))
+                    .route("/{id}", web::get().to(get_record))
+                    .route("/{id}", web::put().to(replace_record))
+                    .route("/{id}", web::patch().to(patch_record))
+                    .route("/{id}", web::delete().to(delete_record))
+            )
+    );
+}
+
+/// 啟動模擬 Cloudflare API 伺服器
+///
+/// 伺服器在背景運行，API 基礎 URL 為 `http://{地址}/client/v4`
+///
+/// # 參數
+///
+/// - `mock`: 模擬 API 的狀態
+/// - `address`: 監聽地址，端口為 0 時由系統分配
+///
+/// # 返回
+///
+/// - `io::Result<(ServerHandle, SocketAddr)>`: 成功時返回用於停止伺服器的控制代碼與實際監聽的地址
+pub fn start(mock: web::Data<MockCloudflare>, address: SocketAddr) -> io::Result<(ServerHandle, SocketAddr)> {
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(mock.clone())
+            .configure(configure_routes)
+    })
+    .workers(1)
+    .bind(address)?;
+
+    let address = server.addrs().first().copied().unwrap_or(address);
+    let server = server.run();
+    let handle = server.handle();
+    tokio::spawn(server);
+
+    info!("Mock Cloudflare API listening on http://{}/client/v4", address);
+    Ok((handle, address))
+}
+
+/// 列出區域
+async fn list_zones(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    query: web::Query<ZoneQuery>,
+) -> impl Responder {
+    if let Err(response) = mock.authorize(&req) {
+        return *response;
+    }
+
+    let zones: Vec<MockZone> = mock.zones()
+        .into_iter()
+        .filter(|zone| query.name.as_ref().is_none_or(|name| name.eq_ignore_ascii_case(&zone.name)))
+        .collect();
+
+    let count = zones.len();
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": zones,
+        "result_info": { "page": 1, "per_page": 50, "count": count, "total_count": count }
+    }))
+}
+
+/// 讀取區域
+async fn get_zone(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Err(response) = mock.authorize(&req) {
+        return *response;
+    }
+
+    match mock.zones.lock().unwrap_or_else(|e| e.into_inner()).get(path.as_str()) {
+        Some(zone) => success_response(zone),
+        None => zone_not_found(&req),
+    }
+}
+
+/// 列出記錄
+async fn list_records(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<String>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    let zone_id = path.into_inner();
+    if let Err(response) = mock.authorize_zone(&req, &zone_id) {
+        return *response;
+    }
+
+    let records: Vec<MockRecord> = mock.lock()
+        .values()
+        .filter(|record| record.zone_id == zone_id)
+        .filter(|record| query.name.as_ref().is_none_or(|name| name.eq_ignore_ascii_case(&record.name)))
+        .filter(|record| query.record_type.as_ref().is_none_or(|record_type| *record_type == record.record_type))
+        .cloned()
+        .collect();
+    debug!("Mock Cloudflare listing {} records in zone {}", records.len(), zone_id);
+
+    let count = records.len();
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": records,
+        "result_info": { "page": 1, "per_page": 100, "count": count, "total_count": count }
+    }))
+}
+
+/// 建立記錄
+async fn create_record(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<String>,
+    body: web::Json<RecordBody>,
+) -> impl Responder {
+    let zone_id = path.into_inner();
+    if let Err(response) = mock.authorize_zone(&req, &zone_id) {
+        return *response;
+    }
+    let body = body.into_inner();
+    let record = MockRecord {
+        id: mock.generate_id(),
+        zone_id,
+        name: body.name,
+        record_type: body.record_type,
+        content: body.content,
+        ttl: body.ttl,
+        proxied: body.proxied,
+        comment: body.comment,
+        tags: body.tags,
+    };
+
+    let mut records = mock.lock();
+    if let Err(response) = validate(&record, records.values()) {
+        return *response;
+    }
+    info!("Mock Cloudflare created {} record {} -> {}", record.record_type, record.name, record.content);
+    records.insert(record.id.clone(), record.clone());
+    success_response(&record)
+}
+
+/// 讀取記錄
+async fn get_record(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (zone_id, id) = path.into_inner();
+    if let Err(response) = mock.authorize_zone(&req, &zone_id) {
+        return *response;
+    }
+
+    match mock.lock().get(&id).filter(|record| record.zone_id == zone_id) {
+        Some(record) => success_response(record),
+        None => record_not_found(),
+    }
+}
+
+/// 取代記錄
+async fn replace_record(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<(String, String)>,
+    body: web::Json<RecordBody>,
+) -> impl Responder {
+    let body = body.into_inner();
+    update_record(&req, &mock, path.into_inner(), |record| {
+        record.name = body.name;
+        record.record_type = body.record_type;
+        record.content = body.content;
+        record.ttl = body.ttl;
+        record.proxied = body.proxied;
+        record.comment = body.comment;
+        record.tags = body.tags;
+    })
+}
+
+/// 部分更新記錄
+async fn patch_record(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<(String, String)>,
+    body: web::Json<RecordPatchBody>,
+) -> impl Responder {
+    let body = body.into_inner();
+    update_record(&req, &mock, path.into_inner(), |record| {
+        if let Some(name) = body.name {
+            record.name = name;
+        }
+        if let Some(record_type) = body.record_type {
+            record.record_type = record_type;
+        }
+        if let Some(content) = body.content {
+            record.content = content;
+        }
+        if let Some(ttl) = body.ttl {
+            record.ttl = ttl;
+        }
+        if let Some(proxied) = body.proxied {
+            record.proxied = proxied;
+        }
+        if body.comment.is_some() {
+            record.comment = body.comment;
+        }
+        if let Some(tags) = body.tags {
+            record.tags = tags;
+        }
+    })
+}
+
+/// 刪除記錄
+async fn delete_record(
+    req: HttpRequest,
+    mock: web::Data<MockCloudflare>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (zone_id, id) = path.into_inner();
+    if let Err(response) = mock.authorize_zone(&req, &zone_id) {
+        return *response;
+    }
+
+    let mut records = mock.lock();
+    if records.get(&id).is_none_or(|record| record.zone_id != zone_id) {
+        return record_not_found();
+    }
+    if let Some(record) = records.remove(&id) {
+        info!("Mock Cloudflare deleted {} record {}", record.record_type, record.name);
+    }
+    success_response(&serde_json::json!({ "id": id }))
+}
+
+/// 修改現有記錄，驗證失敗時保留原本的內容
+fn update_record(
+    req: &HttpRequest,
+    mock: &MockCloudflare,
+    (zone_id, id): (String, String),
+    update: impl FnOnce(&mut MockRecord),
+) -> HttpResponse {
+    if let Err(response) = mock.authorize_zone(req, &zone_id) {
+        return *response;
+    }
+
+    let mut records = mock.lock();
+    let Some(mut record) = records.get(&id).filter(|record| record.zone_id == zone_id).cloned() else {
+        return record_not_found();
+    };
+    update(&mut record);
+
+    if let Err(response) = validate(&record, records.values().filter(|other| other.id != id)) {
+        return *response;
+    }
+    info!("Mock Cloudflare updated {} record {} -> {}", record.record_type, record.name, record.content);
+    records.insert(id, record.clone());
+    success_response(&record)
+}
+
+/// 以 Cloudflare 的規則驗證記錄內容
+fn validate<'a>(record: &MockRecord, others: impl Iterator<Item = &'a MockRecord>) -> Result<(), Box<HttpResponse>> {
+    let valid_content = match record.record_type.as_str() {
+        "A" => record.content.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv4()),
+        "AAAA" => record.content.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()),
+        other => {
+            return reject(StatusCode::BAD_REQUEST, 9020, &format!("Unsupported record type {}", other));
+        }
+    };
+    if !valid_content {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            9005,
+            &format!("Content for {} record is invalid.", record.record_type),
+        );
+    }
+    if record.ttl != 1 && !(60..=86400).contains(&record.ttl) {
+        return reject(StatusCode::BAD_REQUEST, 9021, "Invalid TTL. Must be between 60 and 86400 seconds, or 1 for Automatic.");
+    }
+
+    let mut others = others;
+    if others.any(|other| {
+        other.zone_id == record.zone_id
+            && other.record_type == record.record_type
+            && other.name.eq_ignore_ascii_case(&record.name)
+            && other.content == record.content
+    }) {
+        return reject(StatusCode::BAD_REQUEST, 81058, "An identical record already exists.");
+    }
+    Ok(())
+}
+
+/// 成功響應
+fn success_response(result: &impl Serialize) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": result
+    }))
+}
+
+/// 找不到記錄的響應
+fn record_not_found() -> HttpResponse {
+    error_response(StatusCode::NOT_FOUND, 81044, "Record does not exist.")
+}
+
+/// 找不到區域的響應
+fn zone_not_found(req: &HttpRequest) -> HttpResponse {
+    let path = req.path().strip_prefix("/client/v4").unwrap_or(req.path());
+    error_response(
+        StatusCode::NOT_FOUND,
+        7003,
+        &format!("Could not route to {}, perhaps your object identifier is invalid?", path),
+    )
+}
+
+/// 拒絕請求的錯誤響應（裝箱以避免 `Result` 過大）
+fn reject(status: StatusCode, code: u32, message: &str) -> Result<(), Box<HttpResponse>> {
+    Err(Box::new(error_response(status, code, message)))
+}
+
+/// 錯誤響應
+fn error_response(status: StatusCode, code: u32, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(serde_json::json!({
+        "success": false,
+        "errors": [{ "code": code, "message": message }],
+        "messages": [],
+        "result": null
+    }))
+}
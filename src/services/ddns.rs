@@ -1,28 +1,185 @@
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, AUTHORIZATION};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, AUTHORIZATION};
+use reqwest::StatusCode;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use futures::future::join_all;
 use serde::{Deserialize, Serialize};
 use crate::error::AppError;
-use crate::services::ip;
-use tokio::time::{sleep, Duration};
-use log::{info, error, debug};
+use crate::services::http_client;
+use crate::services::rate_limit::{self, RateLimiter};
+use crate::services::ip::{self, AddressMonitor, IpSourceConfig, IpVersion, NonPublicPolicy};
+use crate::services::retry::{Backoff, ErrorClass, RetryPolicy};
+use crate::services::state::StateStore;
+use tokio::time::{sleep, Duration, Instant};
+use log::{info, warn, error, debug};
+
+/// Cloudflare API 基礎 URL
+const CLOUDFLARE_API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+/// 新建記錄未配置 TTL 時使用的 TTL（秒）
+const DEFAULT_TTL: u32 = 120;
+
+/// 表示認證失敗（令牌無效、過期、權限不足或標頭格式錯誤）的 Cloudflare 錯誤代碼
+const AUTH_ERROR_CODES: &[u32] = &[6003, 6103, 6111, 9103, 9106, 9109, 10000, 10001];
+
+/// 表示找不到區域或記錄的 Cloudflare 錯誤代碼
+const NOT_FOUND_ERROR_CODES: &[u32] = &[1001, 7003, 81044];
+
+/// 表示請求頻率過高的 Cloudflare 錯誤代碼
+const RATE_LIMIT_ERROR_CODES: &[u32] = &[971];
+
+/// 表示請求內容無效的 Cloudflare 錯誤代碼（另外 9000 - 9999 中非認證的代碼皆視為驗證錯誤）
+const VALIDATION_ERROR_CODES: &[u32] = &[1004, 81053, 81057, 81058];
+
+/// 收到 429 響應後，在同一個請求中等待並重試的次數
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// 在同一個請求中等待 `Retry-After` 的上限，超過時直接返回錯誤，由重試策略處理
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(300);
 
 /// DDNS 配置結構
 /// 
 /// # 欄位
 /// 
-/// - `api_token`: Cloudflare API 令牌
+/// - `api_token`: Cloudflare API 令牌（使用 Global API Key 時省略）
+/// - `api_email`: Cloudflare 帳號電子郵件（僅用於 Global API Key 認證）
+/// - `api_key`: Cloudflare Global API Key（可選，取代 `api_token`，需同時設置 `api_email`）
 /// - `zone_id`: Cloudflare 區域 ID
-/// - `record_id`: DNS 記錄 ID
-/// - `record_name`: DNS 記錄名稱
+/// - `api_base_url`: Cloudflare API 基礎 URL（可選，未設置時使用 `https://api.cloudflare.com/client/v4`，用於測試或 API 代理）
+/// - `record_id`: DNS 記錄 ID（可選，未設置時依記錄名稱與類型查詢）
+/// - `record_name`: DNS 記錄名稱（設置 `records` 時可省略）
+/// - `records`: 其他使用同一個偵測結果更新的記錄（可選）
 /// - `update_interval`: 更新間隔（秒）
-/// - `ip_type`: IP 類型（ipv4 或 ipv6）
+/// - `ip_type`: IP 類型（`ipv4`、`ipv6`；`both` 同時管理 A 與 AAAA 記錄，`auto` 只管理主機目前擁有的地址族）
+/// - `ip_sources`: 此記錄使用的 IP 來源備援鏈（可選，未設置時使用全域設置）
+/// - `ip_quorum`: 共識模式所需的一致來源數（可選，未設置時使用全域設置）
+/// - `watch_address_changes`: 是否監聽本機地址變更通知並立即重新檢查（僅限 Linux）
+/// - `non_public_policy`: 偵測到非公網地址時的處理策略（可選，未設置時使用全域設置）
+/// - `create_if_missing`: 找不到記錄時是否自動建立
+/// - `ttl`: 記錄 TTL（秒，1 表示自動；可選，未設置時保留記錄目前的值）
+/// - `proxied`: 是否啟用 Cloudflare 代理（可選，未設置時保留記錄目前的值）
+/// - `comment`: 記錄備註（可選，未設置時保留記錄目前的值）
+/// - `tags`: 記錄標籤（可選，未設置時保留記錄目前的值）
+/// - `reconcile_interval`: 與 Cloudflare 上的記錄核對並修復偏移的間隔（秒，可選，未設置時不核對）
+/// - `reconcile_dns`: 核對時是否同時透過 DNS 解析記錄名稱
+/// - `remove_when_unavailable`: `both` / `auto` 模式下主機失去某個地址族時，是否刪除該類型的記錄（地址恢復後重新建立需要啟用 `create_if_missing`）
+/// - `retry`: 失敗後的重試策略
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DdnsConfig {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub api_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
     pub zone_id: String,
-    pub record_id: String, 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<String>,
+    #[serde(default)]
     pub record_name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<RecordTarget>,
     pub update_interval: u64,  // 更新間隔（秒）
     pub ip_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_sources: Option<Vec<IpSourceConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_quorum: Option<usize>,
+    #[serde(default)]
+    pub watch_address_changes: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_public_policy: Option<NonPublicPolicy>,
+    #[serde(default)]
+    pub create_if_missing: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconcile_interval: Option<u64>,
+    #[serde(default)]
+    pub reconcile_dns: bool,
+    #[serde(default)]
+    pub remove_when_unavailable: bool,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+impl DdnsConfig {
+    /// 此項目要更新的所有記錄
+    /// 
+    /// `record_name` / `record_id` 設置時為第一筆記錄，其後為 `records`
+    pub fn targets(&self) -> Vec<RecordTarget> {
+        let primary = (!self.record_name.trim().is_empty() || self.record_id.is_some()).then(|| RecordTarget {
+            name: Some(self.record_name.clone()).filter(|name| !name.trim().is_empty()),
+            id: self.record_id.clone(),
+        });
+
+        primary.into_iter().chain(self.records.iter().cloned()).collect()
+    }
+
+    /// 此項目使用的 Cloudflare API 認證方式
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<ApiCredentials<'_>, String>`: 設置了 `api_key` 時為 Global API Key 認證，否則為 API 令牌認證；
+    ///   未設置任何認證、同時設置兩者或 Global API Key 缺少電子郵件時返回錯誤描述
+    pub fn credentials(&self) -> Result<ApiCredentials<'_>, String> {
+        let api_key = self.api_key.as_deref().filter(|key| !key.trim().is_empty());
+        let api_email = self.api_email.as_deref().filter(|email| !email.trim().is_empty());
+
+        match (self.api_token.trim().is_empty(), api_key, api_email) {
+            (false, None, _) => Ok(ApiCredentials::Token(&self.api_token)),
+            (false, Some(_), _) => Err("Set either an API token or a Global API Key, not both".to_string()),
+            (true, Some(key), Some(email)) => Ok(ApiCredentials::GlobalKey { email, key }),
+            (true, Some(_), None) => Err("Global API Key requires the account email".to_string()),
+            (true, None, _) => Err("API token cannot be empty".to_string()),
+        }
+    }
+}
+
+/// Cloudflare API 認證方式
+/// 
+/// # 變體
+/// 
+/// - `Token`: API 令牌，以 `Authorization: Bearer` 標頭發送
+/// - `GlobalKey`: 舊版 Global API Key，以 `X-Auth-Email` / `X-Auth-Key` 標頭發送，擁有整個帳號的權限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiCredentials<'a> {
+    Token(&'a str),
+    GlobalKey { email: &'a str, key: &'a str },
+}
+
+impl<'a> ApiCredentials<'a> {
+    /// 識別 Cloudflare 帳號的值（API 令牌或電子郵件），用於共用請求頻率限制
+    pub fn account(&self) -> &'a str {
+        match self {
+            ApiCredentials::Token(token) => token,
+            ApiCredentials::GlobalKey { email, .. } => email,
+        }
+    }
+}
+
+/// DDNS 項目中的單筆記錄
+/// 
+/// # 欄位
+/// 
+/// - `name`: 記錄名稱（可選，未設置時必須設置 `id`）
+/// - `id`: 記錄 ID（可選，未設置時依名稱與類型查詢）
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordTarget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
 /// Cloudflare API 響應結構
@@ -30,20 +187,95 @@ pub struct DdnsConfig {
 /// # 欄位
 /// 
 /// - `success`: 請求是否成功
-/// - `errors`: 錯誤訊息列表 (可選)
+/// - `errors`: 錯誤列表 (可選)
 /// - `messages`: 提示訊息列表 (可選)
 /// - `result`: API 響應結果 (可選)
 #[derive(Serialize, Deserialize, Debug)]
 struct CloudflareResponse {
     success: bool,
     #[serde(default)]
-    errors: Vec<serde_json::Value>,
+    errors: Vec<CloudflareError>,
     #[serde(default)]
     messages: Vec<serde_json::Value>,
     result: Option<serde_json::Value>,
 }
 
-/// DNS 記錄更新結構
+/// Cloudflare API 錯誤
+/// 
+/// # 欄位
+/// 
+/// - `code`: 錯誤代碼
+/// - `message`: 錯誤訊息
+/// - `error_chain`: 導致此錯誤的更詳細錯誤（可選）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CloudflareError {
+    #[serde(default)]
+    code: u32,
+    #[serde(default)]
+    message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    error_chain: Vec<CloudflareError>,
+}
+
+impl CloudflareError {
+    /// 此錯誤或其錯誤鏈中是否有符合的錯誤代碼
+    fn has_code(&self, matches: &impl Fn(u32) -> bool) -> bool {
+        matches(self.code) || self.error_chain.iter().any(|cause| cause.has_code(matches))
+    }
+}
+
+impl fmt::Display for CloudflareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)?;
+        for cause in &self.error_chain {
+            write!(f, ": {}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+/// Cloudflare DNS 記錄
+/// 
+/// # 欄位
+/// 
+/// - `id`: 記錄 ID
+/// - `name`: 記錄名稱
+/// - `record_type`: 記錄類型
+/// - `content`: 記錄內容（IP 地址）
+/// - `ttl`: 記錄 TTL（秒，1 表示自動）
+/// - `proxied`: 是否啟用 Cloudflare 代理
+/// - `comment`: 記錄備註
+/// - `tags`: 記錄標籤
+/// - `modified_on`: 最後修改時間
+/// - `meta`: Cloudflare 附加的記錄資訊（例如修改來源）
+#[derive(Deserialize, Debug, Clone)]
+struct DnsRecord {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default = "default_record_ttl")]
+    ttl: u32,
+    #[serde(default)]
+    proxied: bool,
+    #[serde(default)]
+    comment: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    modified_on: Option<String>,
+    #[serde(default)]
+    meta: Option<serde_json::Value>,
+}
+
+/// Cloudflare 的自動 TTL
+fn default_record_ttl() -> u32 {
+    1
+}
+
+/// DNS 記錄建立結構
 /// 
 /// # 欄位
 /// 
@@ -52,25 +284,156 @@ struct CloudflareResponse {
 /// - `content`: 記錄內容（IP 地址）
 /// - `ttl`: 記錄 TTL（秒）
 /// - `proxied`: 是否啟用 Cloudflare 代理
-#[derive(Serialize, Deserialize, Debug)]
-struct UpdateRecord {
+/// - `comment`: 記錄備註（可選）
+/// - `tags`: 記錄標籤
+#[derive(Serialize, Debug)]
+struct CreateRecord {
     #[serde(rename = "type")]
     record_type: String,
     name: String,
     content: String,
     ttl: u32,
     proxied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    tags: Vec<String>,
+}
+
+/// DNS 記錄部分更新結構
+/// 
+/// 以 PATCH 發送，只包含需要修改的欄位，其他欄位維持不變
+/// 
+/// # 欄位
+/// 
+/// - `content`: 新的記錄內容（IP 地址，可選）
+/// - `ttl`: 新的 TTL（可選）
+/// - `proxied`: 新的代理設定（可選）
+/// - `comment`: 新的備註（可選）
+/// - `tags`: 新的標籤（可選）
+#[derive(Serialize, Debug)]
+struct RecordPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+}
+
+impl RecordPatch {
+    /// 記錄是否已是目前狀態，不需要更新
+    fn is_empty(&self) -> bool {
+        self.content.is_none()
+            && self.ttl.is_none()
+            && self.proxied.is_none()
+            && self.comment.is_none()
+            && self.tags.is_none()
+    }
+}
+
+/// 要更新的 DNS 記錄
+/// 
+/// # 欄位
+/// 
+/// - `name`: 記錄名稱（只設置 ID 時為 `None`）
+/// - `configured_id`: 配置中指定的記錄 ID（可選）
+/// - `id`: 記錄 ID（來自配置，或依名稱查詢後快取）
+/// - `failed`: 記錄因無法重試的錯誤而停止更新，直到服務重新啟動
+struct Record {
+    name: Option<String>,
+    configured_id: Option<String>,
+    id: Mutex<Option<String>>,
+    failed: AtomicBool,
+}
+
+impl Record {
+    /// 根據配置創建記錄
+    fn new(target: RecordTarget) -> Self {
+        Self {
+            id: Mutex::new(target.id.clone()),
+            name: target.name,
+            configured_id: target.id,
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// 記錄是否已因無法重試的錯誤而停止更新
+    fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// 將記錄標記為失敗，之後的檢查略過此記錄
+    fn mark_failed(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    /// 用於日誌與結果的記錄名稱，只設置 ID 時使用 ID
+    fn label(&self) -> &str {
+        self.name.as_deref().or(self.configured_id.as_deref()).unwrap_or_default()
+    }
+
+    /// 目前已知的記錄 ID
+    fn cached_id(&self) -> Option<String> {
+        self.id.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 快取查詢到或剛建立的記錄 ID
+    fn cache_id(&self, id: String) {
+        *self.id.lock().unwrap_or_else(|e| e.into_inner()) = Some(id);
+    }
+
+    /// 記錄已被刪除，包括配置中指定的 ID 也不再有效，之後依名稱重新查詢
+    fn clear_id(&self) {
+        *self.id.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// 清除依名稱查詢到的記錄 ID
+    /// 
+    /// 記錄可能已被刪除或重建，下次更新時重新查詢；配置中指定的記錄 ID 不受影響
+    fn forget_id(&self) {
+        if self.configured_id.is_none() {
+            *self.id.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+}
+
+/// 服務所屬配置項目的地址族模式
+/// 
+/// # 變體
+/// 
+/// - `Single`: 項目只管理一個地址族（`ipv4` 或 `ipv6`）
+/// - `Both`: 雙棧項目的其中一個地址族，無法取得該地址族的地址視為錯誤
+/// - `Auto`: 雙棧項目的其中一個地址族，主機沒有該地址族時略過
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FamilyMode {
+    Single,
+    Both,
+    Auto,
 }
 
 /// DDNS 服務結構
 /// 
+/// 一個服務對應一個配置項目的一個地址族，偵測到的地址會寫入該項目的所有記錄
+/// 
 /// # 欄位
 /// 
-/// - `config`: DDNS 配置
+/// - `config`: DDNS 配置（`ip_type` 為此服務的地址族）
 /// - `client`: HTTP 客戶端
+/// - `limiter`: 與使用相同帳號的服務共用的請求頻率限制器
+/// - `records`: 要更新的記錄
+/// - `state`: 持久化狀態存儲（可選）
+/// - `mode`: 地址族模式
 pub struct DdnsService {
     config: DdnsConfig,
     client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
+    records: Vec<Record>,
+    state: Option<Arc<StateStore>>,
+    mode: FamilyMode,
 }
 
 impl DdnsService {
@@ -82,14 +445,145 @@ impl DdnsService {
     /// 
     /// # 返回
     /// 
-    /// 新的 DDNS 服務實例
-    pub fn new(config: DdnsConfig) -> Self {
-        Self {
+    /// - `Result<Self, AppError>`: 成功時返回新的 DDNS 服務實例，失敗時返回錯誤
+    /// 
+    /// # 錯誤
+    /// 
+    /// 未設置 API 令牌或 Global API Key 與電子郵件時返回 `AppError::ConfigError`
+    pub fn new(config: DdnsConfig) -> Result<Self, AppError> {
+        let limiter = rate_limit::for_account(config.credentials().map_err(AppError::ConfigError)?.account());
+        Ok(Self {
+            records: config.targets().into_iter().map(Record::new).collect(),
+            limiter,
             config,
-            client: reqwest::Client::new(),
+            client: http_client::client(),
+            state: None,
+            mode: FamilyMode::Single,
+        })
+    }
+
+    /// 根據配置創建 DDNS 服務
+    /// 
+    /// `ip_type` 為 `both` 或 `auto` 時，為 IPv4 與 IPv6 各建立一個服務
+    /// 
+    /// # 參數
+    /// 
+    /// - `config`: DDNS 配置
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Vec<Self>, AppError>`: 成功時返回此配置項目的所有 DDNS 服務，失敗時返回錯誤
+    /// 
+    /// # 錯誤
+    /// 
+    /// 與 [`Self::new`] 相同
+    pub fn for_config(config: DdnsConfig) -> Result<Vec<Self>, AppError> {
+        let mode = match config.ip_type.as_str() {
+            "both" => FamilyMode::Both,
+            "auto" => FamilyMode::Auto,
+            _ => return Ok(vec![Self::new(config)?]),
+        };
+
+        ["ipv4", "ipv6"]
+            .into_iter()
+            .map(|ip_type| {
+                let config = DdnsConfig { ip_type: ip_type.to_string(), ..config.clone() };
+                Ok(Self { mode, ..Self::new(config)? })
+            })
+            .collect()
+    }
+
+    /// 使用狀態存儲保存最後的地址、更新時間與錯誤
+    /// 
+    /// # 參數
+    /// 
+    /// - `state`: 狀態存儲，可由多個服務共用
+    pub fn with_state(mut self, state: Arc<StateStore>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// 以逗號分隔的記錄名稱，用於日誌與結果
+    fn describe_records(&self) -> String {
+        self.records.iter().map(Record::label).collect::<Vec<_>>().join(", ")
+    }
+
+    /// 記錄在狀態存儲中的鍵
+    fn state_key(&self, record: &Record) -> String {
+        format!("{}/{}/{}", self.config.zone_id, self.config.ip_type, record.label())
+    }
+
+    /// 更新單筆記錄的持久化狀態（未設置狀態存儲時不做任何事）
+    fn update_record_state(&self, record: &Record, update: impl FnOnce(&StateStore, &str)) {
+        if let Some(state) = &self.state {
+            update(state, &self.state_key(record));
+        }
+    }
+
+    /// 仍在更新的記錄（略過已失敗的記錄）
+    fn active_records(&self) -> Vec<&Record> {
+        self.records.iter().filter(|record| !record.is_failed()).collect()
+    }
+
+    /// 更新所有仍在更新的記錄的持久化狀態（未設置狀態存儲時不做任何事）
+    fn update_state(&self, update: impl Fn(&StateStore, &str)) {
+        for record in self.active_records() {
+            self.update_record_state(record, &update);
+        }
+    }
+
+    /// 將此次檢查的狀態變更寫回文件（未設置狀態存儲時不做任何事）
+    async fn flush_state(&self) {
+        if let Some(state) = &self.state {
+            state.flush().await;
         }
     }
 
+    /// 從狀態存儲恢復上次發布的地址
+    /// 
+    /// # 返回
+    /// 
+    /// - `Option<IpAddr>`: 所有記錄都已發布同一個地址時返回該地址，否則返回 `None`
+    fn restore_published_ip(&self) -> Option<IpAddr> {
+        let state = self.state.as_ref()?;
+        let mut published = self.records.iter()
+            .map(|record| state.get(&self.state_key(record)).and_then(|state| state.published_ip));
+
+        let first = published.next()??;
+        published.all(|ip| ip == Some(first)).then_some(first)
+    }
+
+    /// 使用此記錄配置的 IP 來源獲取當前 IP
+    /// 
+    /// 透過共用的 IP 觀察者查詢，相同來源配置的記錄與 API 請求共用查詢結果
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<IpAddr, AppError>`: 成功時返回當前 IP 地址，失敗時返回錯誤
+    /// 
+    /// # 錯誤
+    /// 
+    /// 當以下情況發生時返回錯誤：
+    /// - IP 類型或來源配置無效
+    /// - 所有 IP 來源都失敗，或未達成共識
+    /// - 來源返回無效的 IP 地址
+    pub async fn fetch_current_ip(&self) -> Result<IpAddr, AppError> {
+        self.observe_ip(None).await
+    }
+
+    /// 透過共用的 IP 觀察者獲取當前 IP
+    /// 
+    /// # 參數
+    /// 
+    /// - `changed_since`: 已知本機地址在此時間點變更，早於此時間的快取結果不會被使用（可選）
+    async fn observe_ip(&self, changed_since: Option<Instant>) -> Result<IpAddr, AppError> {
+        let version = IpVersion::from_ip_type(&self.config.ip_type)?;
+        let sources = self.config.ip_sources.clone().unwrap_or_else(ip::default_sources);
+        let quorum = self.config.ip_quorum.or(ip::settings().quorum);
+        
+        ip::observer().observe(&sources, quorum, version, changed_since).await
+    }
+
     /// 更新 DNS 記錄
     /// 
     /// # 返回
@@ -100,79 +594,636 @@ impl DdnsService {
     /// 
     /// 當以下情況發生時返回錯誤：
     /// - 獲取當前 IP 失敗
+    /// - 當前 IP 不可公開路由且策略為 `fail`
     /// - API 請求失敗
     /// - 響應解析失敗
     pub async fn update_record(&self) -> Result<serde_json::Value, AppError> {
         // 根據 IP 類型獲取當前 IP
-        let (current_ip, record_type) = match self.config.ip_type.as_str() {
-            "ipv4" => {
-                let ip = ip::fetch_ipv4().await?;
-                debug!("Current IPv4 address: {}", ip);
-                (ip, "A")
-            },
-            "ipv6" => {
-                let ip = ip::fetch_ipv6().await?;
-                debug!("Current IPv6 address: {}", ip);
-                (ip, "AAAA")
+        let current_ip = self.fetch_current_ip().await?;
+        debug!("Current {} address: {}", self.config.ip_type, current_ip);
+        
+        if !self.should_publish(current_ip)? {
+            return Ok(serde_json::json!({
+                "status": "skipped",
+                "message": format!("{} address {} is not publicly routable, DNS record left unchanged", self.config.ip_type, current_ip),
+                "data": {
+                    "ip": current_ip,
+                    "class": ip::classify(current_ip),
+                    "domain": self.describe_records()
+                }
+            }));
+        }
+        
+        self.update_record_with_ip(current_ip).await
+    }
+
+    /// 依非公網地址策略判斷是否應發布此地址
+    /// 
+    /// # 參數
+    /// 
+    /// - `current_ip`: 偵測到的 IP 地址
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<bool, AppError>`: 應發布時返回 `true`，應略過時返回 `false`；策略為 `fail` 時返回 `AppError::NonPublicAddress`
+    pub fn should_publish(&self, current_ip: IpAddr) -> Result<bool, AppError> {
+        let class = ip::classify(current_ip);
+        if class.is_public() {
+            return Ok(true);
+        }
+        
+        let policy = self.config.non_public_policy.unwrap_or(ip::settings().non_public_policy);
+        match policy {
+            NonPublicPolicy::Warn => {
+                warn!("{} address {} is {}, publishing anyway", self.config.ip_type, current_ip, class);
+                Ok(true)
+            }
+            NonPublicPolicy::Skip => {
+                warn!("{} address {} is {}, skipping update of {}", self.config.ip_type, current_ip, class, self.describe_records());
+                Ok(false)
+            }
+            NonPublicPolicy::Fail => Err(AppError::NonPublicAddress(format!("{} address {} is {}", self.config.ip_type, current_ip, class))),
+        }
+    }
+
+    /// 使用指定的 IP 地址更新此項目的所有 DNS 記錄
+    /// 
+    /// 所有記錄同時更新。每筆記錄先讀取目前的內容，已是該地址且設定相符時不發送寫入請求；
+    /// 否則以 PATCH 只更新不同的欄位
+    /// 
+    /// # 參數
+    /// 
+    /// - `current_ip`: 要寫入記錄的 IP 地址
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<serde_json::Value, AppError>`: 成功時返回更新結果（多筆記錄時 `data.records` 為各記錄的結果），
+    ///   任一記錄失敗時返回第一個錯誤
+    /// 
+    /// # 錯誤
+    /// 
+    /// 當以下情況發生時返回錯誤：
+    /// - IP 地址版本與記錄類型不符
+    /// - 找不到記錄且未啟用 `create_if_missing`
+    /// - API 請求失敗
+    /// - 響應解析失敗
+    pub async fn update_record_with_ip(&self, current_ip: IpAddr) -> Result<serde_json::Value, AppError> {
+        let record_type = self.record_type(current_ip)?;
+        let records = self.active_records();
+        let results = join_all(records.iter().map(|record| self.update_one(record, record_type, current_ip))).await;
+
+        let mut results = self.track_results(&records, results, |record, _| {
+            self.update_record_state(record, |state, key| state.record_success(key, current_ip));
+        })?;
+        if results.len() == 1 {
+            return Ok(results.remove(0));
+        }
+
+        Ok(serde_json::json!({
+            "status": "success",
+            "message": format!("{} {} DNS records processed", results.len(), self.config.ip_type),
+            "data": {
+                "ip": current_ip,
+                "records": results
+            }
+        }))
+    }
+
+    /// 記錄每筆記錄的結果並合併
+    /// 
+    /// 無法重試的錯誤只讓該記錄標記為失敗，之後的檢查略過它，其餘記錄繼續更新
+    /// 
+    /// # 參數
+    /// 
+    /// - `records`: 此次處理的記錄
+    /// - `results`: 依 `records` 順序排列的結果
+    /// - `on_success`: 記錄成功時呼叫
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Vec<T>, AppError>`: 有可重試的錯誤時返回第一個；所有記錄都已失敗時返回第一個無法重試的錯誤；
+    ///   否則返回成功記錄的結果
+    fn track_results<T>(
+        &self,
+        records: &[&Record],
+        results: Vec<Result<T, AppError>>,
+        on_success: impl Fn(&Record, &T),
+    ) -> Result<Vec<T>, AppError> {
+        let mut values = Vec::with_capacity(results.len());
+        let mut first_error = None;
+        let mut fatal_error = None;
+
+        for (record, result) in records.iter().zip(results) {
+            match result {
+                Ok(value) => {
+                    on_success(record, &value);
+                    values.push(value);
+                }
+                Err(e) if ErrorClass::is_fatal(&e) => {
+                    record.mark_failed();
+                    self.update_record_state(record, |state, key| state.record_failed(key, &e));
+                    if self.records.len() > 1 {
+                        error!(
+                            "{} record {} failed and will be skipped until the service is restarted: {}",
+                            self.config.ip_type, record.label(), e
+                        );
+                    }
+                    fatal_error.get_or_insert(e);
+                }
+                Err(e) => {
+                    self.update_record_state(record, |state, key| state.record_error(key, &e));
+                    if self.records.len() > 1 {
+                        error!("{} record {} failed: {}", self.config.ip_type, record.label(), e);
+                    }
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match (first_error, fatal_error) {
+            (Some(e), _) => Err(e),
+            (None, Some(e)) if self.records.iter().all(Record::is_failed) => Err(e),
+            _ => Ok(values),
+        }
+    }
+
+    /// 取得與 IP 地址對應的記錄類型
+    /// 
+    /// # 錯誤
+    /// 
+    /// IP 地址版本與配置的 IP 類型不符時返回錯誤
+    fn record_type(&self, current_ip: IpAddr) -> Result<&'static str, AppError> {
+        match (IpVersion::from_ip_type(&self.config.ip_type)?, current_ip) {
+            (IpVersion::V4, IpAddr::V4(_)) | (IpVersion::V6, IpAddr::V6(_)) => self.configured_record_type(),
+            (version, ip) => Err(AppError::InvalidIpResponse(format!("expected {} address, got {}", version, ip))),
+        }
+    }
+
+    /// 取得此服務管理的記錄類型
+    fn configured_record_type(&self) -> Result<&'static str, AppError> {
+        match IpVersion::from_ip_type(&self.config.ip_type)? {
+            IpVersion::V4 => Ok("A"),
+            IpVersion::V6 => Ok("AAAA"),
+        }
+    }
+
+    /// 失去地址族時是否刪除記錄（僅限 `both` / `auto` 項目）
+    fn removes_when_unavailable(&self) -> bool {
+        self.mode != FamilyMode::Single && self.config.remove_when_unavailable
+    }
+
+    /// 使用指定的 IP 地址更新單筆 DNS 記錄
+    /// 
+    /// # 參數
+    /// 
+    /// - `record`: 要更新的記錄
+    /// - `record_type`: 記錄類型（A 或 AAAA）
+    /// - `current_ip`: 要寫入記錄的 IP 地址
+    async fn update_one(&self, record: &Record, record_type: &str, current_ip: IpAddr) -> Result<serde_json::Value, AppError> {
+        let record_id = self.resolve_record_id(record, record_type).await?;
+        let (request, ttl, proxied, action) = match (&record_id, &record.name) {
+            (Some(record_id), _) => {
+                let existing = self.get_record(record, record_id).await?;
+                let patch = self.record_patch(&existing, current_ip);
+                if patch.is_empty() {
+                    info!("{} record {} already points to {}, skipping update", record_type, record.label(), current_ip);
+                    return Ok(serde_json::json!({
+                        "status": "success",
+                        "message": format!("{} DNS record already up to date", self.config.ip_type),
+                        "data": {
+                            "ip": current_ip,
+                            "domain": record.label(),
+                            "ttl": existing.ttl,
+                            "proxied": existing.proxied
+                        }
+                    }));
+                }
+
+                info!("Preparing to update {} DNS record {}: {:?}", self.config.ip_type, record.label(), patch);
+                let url = format!(
+                    "{}/zones/{}/dns_records/{}", 
+                    self.api_base(), self.config.zone_id, record_id
+                );
+                debug!("Sending request to: {}", url);
+                let ttl = patch.ttl.unwrap_or(existing.ttl);
+                let proxied = patch.proxied.unwrap_or(existing.proxied);
+                (self.client.patch(url).json(&patch), ttl, proxied, "updated")
+            }
+            // 失去地址族時會刪除記錄的項目，在恢復後必須重新建立
+            (None, Some(name)) if self.config.create_if_missing => {
+                let create_data = CreateRecord {
+                    record_type: record_type.to_string(),
+                    name: name.clone(),
+                    content: current_ip.to_string(),
+                    ttl: self.config.ttl.unwrap_or(DEFAULT_TTL),
+                    proxied: self.config.proxied.unwrap_or(false),
+                    comment: self.config.comment.clone(),
+                    tags: self.config.tags.clone().unwrap_or_default(),
+                };
+                info!("{} record {} does not exist, creating it: {:?}", record_type, name, create_data);
+                let url = format!("{}/zones/{}/dns_records", self.api_base(), self.config.zone_id);
+                debug!("Sending request to: {}", url);
+                let (ttl, proxied) = (create_data.ttl, create_data.proxied);
+                (self.client.post(url).json(&create_data), ttl, proxied, "created")
+            }
+            (None, _) => return Err(AppError::NotFound(format!(
+                "{} record {} not found in zone {}", record_type, record.label(), self.config.zone_id
+            ))),
+        };
+
+        let cf_response = self.send(request).await.inspect_err(|e| {
+            error!(
+                "Failed to {} {} DNS record {}: {}",
+                if record_id.is_some() { "update" } else { "create" }, self.config.ip_type, record.label(), e
+            );
+            if matches!(e, AppError::NotFound(_)) {
+                record.forget_id();
+            }
+        })?;
+
+        if record_id.is_none() {
+            self.cache_created_record_id(record, cf_response.result.as_ref());
+        }
+        info!("Cloudflare API returned successful response");
+        Ok(serde_json::json!({
+            "status": "success",
+            "message": format!("{} DNS record {}", self.config.ip_type, action),
+            "data": {
+                "ip": current_ip,
+                "domain": record.label(),
+                "ttl": ttl,
+                "proxied": proxied
+            }
+        }))
+    }
+
+    /// 計算將記錄更新為目前狀態所需修改的欄位
+    /// 
+    /// 只包含與記錄目前內容不同的欄位；未配置的設定保留記錄目前的值
+    /// 
+    /// # 參數
+    /// 
+    /// - `existing`: 記錄目前的內容
+    /// - `current_ip`: 要寫入記錄的 IP 地址
+    fn record_patch(&self, existing: &DnsRecord, current_ip: IpAddr) -> RecordPatch {
+        // 以解析後的地址比較，避免 IPv6 表示法不同造成誤判
+        let content_matches = existing.content.parse::<IpAddr>().is_ok_and(|ip| ip == current_ip);
+
+        RecordPatch {
+            content: (!content_matches).then(|| current_ip.to_string()),
+            ttl: self.config.ttl.filter(|ttl| *ttl != existing.ttl),
+            proxied: self.config.proxied.filter(|proxied| *proxied != existing.proxied),
+            comment: self.config.comment.clone()
+                .filter(|comment| existing.comment.as_deref().unwrap_or_default() != comment),
+            tags: self.config.tags.clone().filter(|tags| {
+                let mut wanted = tags.clone();
+                let mut current = existing.tags.clone();
+                wanted.sort();
+                current.sort();
+                wanted != current
+            }),
+        }
+    }
+
+    /// 與 Cloudflare 上的記錄核對，修復在服務之外被修改的內容
+    /// 
+    /// 所有記錄同時核對。記錄被修改或刪除時記錄修改資訊並重新寫入；啟用 `reconcile_dns` 時，
+    /// 另外檢查記錄名稱透過 DNS 解析的結果（僅記錄警告，DNS 快取可能尚未過期）
+    /// 
+    /// # 參數
+    /// 
+    /// - `current_ip`: 記錄應指向的 IP 地址
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<bool, AppError>`: 修復了任一記錄時返回 `true`，全部一致時返回 `false`，任一記錄失敗時返回錯誤
+    pub async fn reconcile(&self, current_ip: IpAddr) -> Result<bool, AppError> {
+        let record_type = self.record_type(current_ip)?;
+        let records = self.active_records();
+        let results = join_all(records.iter().map(|record| self.reconcile_one(record, record_type, current_ip))).await;
+
+        let repaired = self.track_results(&records, results, |record, repaired| {
+            if *repaired {
+                self.update_record_state(record, |state, key| state.record_success(key, current_ip));
+            }
+        })?;
+        Ok(repaired.contains(&true))
+    }
+
+    /// 核對單筆記錄
+    /// 
+    /// # 參數
+    /// 
+    /// - `record`: 要核對的記錄
+    /// - `record_type`: 記錄類型（A 或 AAAA）
+    /// - `current_ip`: 記錄應指向的 IP 地址
+    async fn reconcile_one(&self, record: &Record, record_type: &str, current_ip: IpAddr) -> Result<bool, AppError> {
+        let existing = match self.resolve_record_id(record, record_type).await? {
+            Some(record_id) => match self.get_record(record, &record_id).await {
+                Ok(existing) => Some(existing),
+                // 記錄已被刪除：清除記錄 ID（包括配置中指定的），依名稱重新查詢或建立
+                Err(AppError::NotFound(_)) if record.name.is_some() => {
+                    record.clear_id();
+                    None
+                }
+                Err(e) => return Err(e),
             },
-            _ => return Err(AppError::ConfigError(format!("Invalid IP type: {}", self.config.ip_type)))
+            None => None,
         };
 
-        let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", self.config.api_token))
-            .map_err(|_| AppError::ConfigError("Invalid API token".to_string()))?);
-
-        let update_data = UpdateRecord {
-            record_type: record_type.to_string(),
-            name: self.config.record_name.clone(),
-            content: current_ip.clone(),
-            ttl: 120,
-            proxied: false,
+        match &existing {
+            Some(existing) if existing.content.parse::<IpAddr>().is_ok_and(|ip| ip == current_ip) => {
+                debug!("{} record {} is in sync ({})", record_type, record.label(), current_ip);
+                if self.config.reconcile_dns && !existing.proxied {
+                    self.check_resolution(&existing.name, current_ip).await;
+                }
+                return Ok(false);
+            }
+            Some(existing) => warn!(
+                "{} record {} was changed outside DDNS: content {} (expected {}), modified on {}, comment {:?}, meta {}",
+                record_type,
+                record.label(),
+                existing.content,
+                current_ip,
+                existing.modified_on.as_deref().unwrap_or("unknown"),
+                existing.comment,
+                existing.meta.as_ref().map(|meta| meta.to_string()).unwrap_or_else(|| "none".to_string())
+            ),
+            None => warn!("{} record {} was deleted outside DDNS", record_type, record.label()),
+        }
+
+        let result = self.update_one(record, record_type, current_ip).await?;
+        info!("Repaired {} record {}: {}", record_type, record.label(), result["message"].as_str().unwrap_or_default());
+        Ok(true)
+    }
+
+    /// 透過系統解析器解析記錄名稱，與應有的地址比對
+    /// 
+    /// # 參數
+    /// 
+    /// - `name`: 記錄名稱
+    /// - `current_ip`: 記錄應指向的 IP 地址
+    async fn check_resolution(&self, name: &str, current_ip: IpAddr) {
+        let resolved: Vec<IpAddr> = match tokio::net::lookup_host((name, 0)).await {
+            Ok(addresses) => addresses
+                .map(|address| address.ip())
+                .filter(|ip| ip.is_ipv4() == current_ip.is_ipv4())
+                .collect(),
+            Err(e) => {
+                warn!("Failed to resolve {}: {}", name, e);
+                return;
+            }
         };
-        info!("Preparing to update {} DNS record: {:?}", self.config.ip_type, update_data);
 
-        let url = format!(
-            "https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}", 
-            self.config.zone_id, self.config.record_id
-        );
+        if resolved.contains(&current_ip) {
+            debug!("{} resolves to {} via DNS", name, current_ip);
+        } else {
+            warn!(
+                "{} resolves to {:?} via DNS, expected {} (resolver caches may not have expired yet)",
+                name, resolved, current_ip
+            );
+        }
+    }
+
+    /// 刪除此項目中此地址族的所有記錄
+    /// 
+    /// 用於主機失去該地址族時，避免記錄繼續指向已失效的地址
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<usize, AppError>`: 成功時返回刪除的記錄數（記錄本來就不存在時不計），任一記錄失敗時返回錯誤
+    pub async fn remove_records(&self) -> Result<usize, AppError> {
+        let record_type = self.configured_record_type()?;
+        let records = self.active_records();
+        let results = join_all(records.iter().map(|record| self.remove_one(record, record_type))).await;
+
+        let removed = self.track_results(&records, results, |record, removed| {
+            if *removed {
+                self.update_record_state(record, |state, key| state.record_removed(key));
+            }
+        })?;
+        Ok(removed.into_iter().filter(|removed| *removed).count())
+    }
+
+    /// 刪除單筆記錄
+    /// 
+    /// # 參數
+    /// 
+    /// - `record`: 要刪除的記錄
+    /// - `record_type`: 記錄類型（A 或 AAAA）
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<bool, AppError>`: 刪除時返回 `true`，記錄不存在時返回 `false`，失敗時返回錯誤
+    async fn remove_one(&self, record: &Record, record_type: &str) -> Result<bool, AppError> {
+        let Some(record_id) = self.resolve_record_id(record, record_type).await? else {
+            debug!("{} record {} does not exist, nothing to remove", record_type, record.label());
+            return Ok(false);
+        };
+
+        let url = format!("{}/zones/{}/dns_records/{}", self.api_base(), self.config.zone_id, record_id);
         debug!("Sending request to: {}", url);
+        match self.send(self.client.delete(url)).await {
+            Ok(_) => {}
+            // 記錄已在服務之外被刪除
+            Err(AppError::NotFound(e)) => {
+                debug!("{} record {} was already removed: {}", record_type, record.label(), e);
+                record.clear_id();
+                return Ok(false);
+            }
+            Err(e) => {
+                error!("Failed to remove {} record {}: {}", record_type, record.label(), e);
+                return Err(e);
+            }
+        }
 
-        let res = self.client.put(&url)
-            .headers(headers)
-            .json(&update_data)
-            .send()
-            .await
-            .map_err(|e| {
-                error!("API request failed: {}", e);
-                AppError::ExternalServiceError(e.to_string())
-            })?;
-
-        let cf_response: CloudflareResponse = res.json()
-            .await
-            .map_err(|e| {
-                error!("Failed to parse API response: {}", e);
-                AppError::ExternalServiceError(e.to_string())
-            })?;
+        info!("Removed {} record {} ({})", record_type, record.label(), record_id);
+        record.clear_id();
+        Ok(true)
+    }
 
-        if cf_response.success {
-            let result = serde_json::json!({
-                "status": "success",
-                "message": format!("{} DNS record updated", self.config.ip_type),
-                "data": {
-                    "ip": current_ip,
-                    "domain": self.config.record_name,
-                    "ttl": 120,
-                    "proxied": false
+    /// 取得要更新的記錄 ID
+    /// 
+    /// 配置中未設置記錄 ID 時，依記錄名稱與類型查詢 Cloudflare 並快取結果
+    /// 
+    /// # 參數
+    /// 
+    /// - `record`: 記錄
+    /// - `record_type`: 記錄類型（A 或 AAAA）
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Option<String>, AppError>`: 成功時返回記錄 ID，記錄不存在時返回 `None`，API 請求失敗時返回錯誤
+    async fn resolve_record_id(&self, record: &Record, record_type: &str) -> Result<Option<String>, AppError> {
+        if let Some(record_id) = record.cached_id() {
+            return Ok(Some(record_id));
+        }
+        let Some(name) = &record.name else {
+            return Ok(None);
+        };
+
+        let records = self.list_records(name, record_type).await?;
+        let found = match records.as_slice() {
+            [] => return Ok(None),
+            [found] => found,
+            [found, ..] => {
+                warn!("Found {} {} records named {}, updating {}", records.len(), record_type, name, found.id);
+                found
+            }
+        };
+        info!("Resolved {} record {} to ID {}", found.record_type, found.name, found.id);
+
+        record.cache_id(found.id.clone());
+        Ok(Some(found.id.clone()))
+    }
+
+    /// 快取剛建立的記錄 ID，之後的更新直接使用
+    /// 
+    /// # 參數
+    /// 
+    /// - `record`: 記錄
+    /// - `result`: 建立記錄時 Cloudflare 返回的 `result`
+    fn cache_created_record_id(&self, record: &Record, result: Option<&serde_json::Value>) {
+        match result.cloned().map(serde_json::from_value::<DnsRecord>) {
+            Some(Ok(created)) => {
+                info!("Created {} record {} with ID {}", created.record_type, created.name, created.id);
+                record.cache_id(created.id);
+            }
+            _ => warn!("Created record {} but could not read its ID, it will be looked up on the next update", record.label()),
+        }
+    }
+
+    /// 依名稱與類型列出區域中的 DNS 記錄
+    /// 
+    /// # 參數
+    /// 
+    /// - `name`: 記錄名稱
+    /// - `record_type`: 記錄類型（A 或 AAAA）
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<Vec<DnsRecord>, AppError>`: 成功時返回符合的記錄，失敗時返回錯誤
+    async fn list_records(&self, name: &str, record_type: &str) -> Result<Vec<DnsRecord>, AppError> {
+        let url = format!("{}/zones/{}/dns_records", self.api_base(), self.config.zone_id);
+        debug!("Looking up {} record {} at {}", record_type, name, url);
+
+        let request = self.client.get(&url)
+            .query(&[("type", record_type), ("name", name)]);
+        let cf_response = self.send(request).await
+            .inspect_err(|e| error!("Failed to look up {} record {}: {}", record_type, name, e))?;
+
+        serde_json::from_value(cf_response.result.unwrap_or_default())
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to parse DNS records: {}", e)))
+    }
+
+    /// 讀取 DNS 記錄目前的內容與設定
+    /// 
+    /// # 參數
+    /// 
+    /// - `record`: 記錄，記錄不存在時清除快取的 ID
+    /// - `record_id`: 記錄 ID
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<DnsRecord, AppError>`: 成功時返回記錄，失敗時返回錯誤
+    async fn get_record(&self, record: &Record, record_id: &str) -> Result<DnsRecord, AppError> {
+        let url = format!("{}/zones/{}/dns_records/{}", self.api_base(), self.config.zone_id, record_id);
+        debug!("Reading DNS record from: {}", url);
+
+        let cf_response = self.send(self.client.get(&url)).await.inspect_err(|e| {
+            error!("Failed to read {} DNS record {} ({}): {}", self.config.ip_type, record.label(), record_id, e);
+            if matches!(e, AppError::NotFound(_)) {
+                record.forget_id();
+            }
+        })?;
+
+        serde_json::from_value(cf_response.result.unwrap_or_default())
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to parse DNS record: {}", e)))
+    }
+
+    /// 發送 Cloudflare API 請求並解析響應
+    /// 
+    /// 請求經過共用的頻率限制器；收到 429 響應時暫停使用相同認證的所有請求直到 `Retry-After`，
+    /// 之後重新發送（最多 `RATE_LIMIT_RETRIES` 次）
+    /// 
+    /// # 參數
+    /// 
+    /// - `request`: 尚未加上認證標頭的請求
+    /// 
+    /// # 返回
+    /// 
+    /// - `Result<CloudflareResponse, AppError>`: 成功時返回 API 響應；請求失敗、響應無法解析或 API 返回錯誤時，
+    ///   依 HTTP 狀態與錯誤代碼返回對應的錯誤（參見 [`classify_api_error`]）
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<CloudflareResponse, AppError> {
+        let mut request = request.headers(self.headers()?);
+        let mut retries = 0;
+        let res = loop {
+            let retry = request.try_clone();
+            self.limiter.acquire().await;
+            let res = request
+                .send()
+                .await
+                .map_err(|e| {
+                    error!("API request failed: {}", e);
+                    AppError::ExternalServiceError(e.to_string())
+                })?;
+            if res.status() != StatusCode::TOO_MANY_REQUESTS {
+                break res;
+            }
+
+            let delay = rate_limit::retry_after(res.headers());
+            self.limiter.pause(delay);
+            match retry {
+                Some(retry) if retries < RATE_LIMIT_RETRIES && delay <= MAX_RATE_LIMIT_WAIT => {
+                    warn!("Cloudflare API rate limit reached, pausing requests for {} seconds", delay.as_secs());
+                    retries += 1;
+                    request = retry;
                 }
-            });
-            info!("Cloudflare API returned successful response");
-            Ok(result)
-        } else {
-            let error_msg = format!("Cloudflare API error: {}", serde_json::to_string(&cf_response.errors).unwrap_or_else(|_| format!("{:?}", cf_response.errors)));
-            error!("Failed to update {} DNS record: {}", self.config.ip_type, error_msg);
-            Err(AppError::ExternalServiceError(error_msg))
+                _ => break res,
+            }
+        };
+        let status = res.status();
+
+        let cf_response: CloudflareResponse = match res.json().await {
+            Ok(cf_response) => cf_response,
+            // 部分錯誤（例如邊緣節點的頻率限制）不會返回 API 格式的響應
+            Err(_) if !status.is_success() => return Err(classify_api_error(status, &[])),
+            Err(e) => {
+                error!("Failed to parse API response: {}", e);
+                return Err(AppError::ExternalServiceError(e.to_string()));
+            }
+        };
+
+        if !cf_response.success {
+            return Err(classify_api_error(status, &cf_response.errors));
+        }
+        Ok(cf_response)
+    }
+
+    /// 此項目使用的 Cloudflare API 基礎 URL（不含結尾的 `/`）
+    fn api_base(&self) -> &str {
+        self.config.api_base_url.as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .unwrap_or(CLOUDFLARE_API_BASE)
+    }
+
+    /// 構建 Cloudflare API 請求標頭
+    fn headers(&self) -> Result<HeaderMap, AppError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        match self.config.credentials().map_err(AppError::ConfigError)? {
+            ApiCredentials::Token(token) => {
+                headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))
+                    .map_err(|_| AppError::ConfigError("Invalid API token".to_string()))?);
+            }
+            ApiCredentials::GlobalKey { email, key } => {
+                headers.insert(HeaderName::from_static("x-auth-email"), HeaderValue::from_str(email)
+                    .map_err(|_| AppError::ConfigError("Invalid API email".to_string()))?);
+                headers.insert(HeaderName::from_static("x-auth-key"), HeaderValue::from_str(key)
+                    .map_err(|_| AppError::ConfigError("Invalid Global API Key".to_string()))?);
+            }
         }
+        Ok(headers)
     }
 
     /// 開始自動更新 DNS 記錄
@@ -184,77 +1235,347 @@ impl DdnsService {
     /// # 行為
     /// 
     /// - 根據配置的間隔定期檢查 IP
-    /// - 只在 IP 變更時更新 DNS 記錄
-    /// - 錯誤時會等待後重試
+    /// - 啟用 `watch_address_changes` 時，本機地址變更會立即觸發檢查，輪詢仍作為備援
+    /// - 只在 IP 變更時更新 DNS 記錄；啟動後的第一次檢查會先比對記錄目前的內容
+    /// - 設置狀態存儲時，從中恢復上次發布的地址，並保存每次檢查的結果；恢復地址後的第一次檢查與 Cloudflare 上的記錄核對，
+    ///   確保停機期間被修改或刪除的記錄得到修復
+    /// - 設置 `reconcile_interval` 時，IP 未變更的檢查會定期與 Cloudflare 上的記錄核對並修復偏移
+    /// - 錯誤時依重試策略等待後重試：暫時性錯誤以帶隨機偏移的指數退避重試，持續性錯誤以較長的間隔重試
+    /// - Cloudflare 拒絕認證或請求內容等無法重試的錯誤，將失敗的記錄標記為失敗，之後的檢查略過它並繼續更新其餘記錄；
+    ///   所有記錄都失敗時停止此服務
     pub async fn start_auto_update(&self) {
         let interval = Duration::from_secs(self.config.update_interval);
-        let mut last_ip = String::new();
+        let mut last_ip = self.restore_published_ip();
+        if let Some(ip) = last_ip {
+            info!("{} restored last published address {} for {}", self.config.ip_type, ip, self.describe_records());
+        }
+        let mut monitor = self.create_address_monitor();
+        let mut changed_at: Option<Instant> = None;
+        let reconcile_interval = self.config.reconcile_interval.map(Duration::from_secs);
+        let mut last_reconciled = Instant::now();
+        // 從狀態恢復地址時尚未讀取過 Cloudflare 上的記錄，第一次檢查先核對
+        let mut reconcile_pending = last_ip.is_some();
+        // 雙棧項目中此地址族目前是否可用，用於只在狀態轉變時記錄與刪除記錄
+        let mut family_available = true;
+        let mut backoff = Backoff::new(self.config.retry.clone());
         
         info!("Starting {} DDNS auto-update service, update interval: {} seconds", self.config.ip_type, self.config.update_interval);
         
         loop {
             // 檢查 IP 是否變更
-            let current_ip = match self.config.ip_type.as_str() {
-                "ipv4" => match ip::fetch_ipv4().await {
-                    Ok(ip) => {
-                        debug!("Successfully obtained IPv4 address: {}", ip);
-                        ip
-                    }
-                    Err(e) => {
-                        error!("Failed to get IPv4: {}, retrying in 60 seconds", e);
-                        sleep(Duration::from_secs(60)).await;
-                        continue;
+            let current_ip = match self.observe_ip(changed_at.take()).await {
+                Ok(ip) => {
+                    debug!("Successfully obtained {} address: {}", self.config.ip_type, ip);
+                    if !family_available {
+                        info!("{} connectivity restored for {}", self.config.ip_type, self.describe_records());
+                        family_available = true;
                     }
-                },
-                "ipv6" => match ip::fetch_ipv6().await {
-                    Ok(ip) => {
-                        info!("Successfully obtained IPv6 address: {}", ip);
-                        ip
-                    }
-                    Err(e) => {
-                        error!("Failed to get IPv6: {}, retrying in 60 seconds", e);
-                        sleep(Duration::from_secs(60)).await;
-                        continue;
+                    self.update_state(|state, key| state.record_observed(key, ip));
+                    ip
+                }
+                Err(e) if ErrorClass::is_fatal(&e) => {
+                    self.update_state(|state, key| state.record_failed(key, &e));
+                    self.stop_after(&e).await;
+                    return;
+                }
+                Err(AppError::AddressFamilyUnavailable(e)) if self.mode != FamilyMode::Single => {
+                    let delay = match self.mode {
+                        FamilyMode::Auto => {
+                            if family_available {
+                                info!("Host has no {} connectivity ({}), will check again in {} seconds", self.config.ip_type, e, self.config.update_interval);
+                            }
+                            interval
+                        }
+                        _ => {
+                            let e = AppError::AddressFamilyUnavailable(e);
+                            let delay = backoff.next_delay(&e);
+                            error!("Failed to get {}: {}, retrying in {} seconds", self.config.ip_type, e, delay.as_secs());
+                            self.update_state(|state, key| state.record_error(key, &e));
+                            delay
+                        }
+                    };
+                    
+                    if family_available && self.removes_when_unavailable() {
+                        match self.remove_records().await {
+                            Ok(removed) => {
+                                if removed > 0 {
+                                    info!("Removed {} {} records after losing {} connectivity", removed, self.configured_record_type().unwrap_or_default(), self.config.ip_type);
+                                }
+                                family_available = false;
+                                last_ip = None;
+                            }
+                            Err(e) if ErrorClass::is_fatal(&e) => {
+                                self.stop_after(&e).await;
+                                return;
+                            }
+                            // 保持可用狀態，下次檢查時重試刪除
+                            Err(e) => error!("Failed to remove {} records: {}", self.config.ip_type, e),
+                        }
+                    } else {
+                        family_available = false;
                     }
-                },
-                _ => {
-                    error!("Invalid IP type: {}, will retry in {} seconds", self.config.ip_type, self.config.update_interval);
-                    sleep(interval).await;
+                    
+                    changed_at = self.wait_for_next_check(&mut monitor, delay).await;
+                    continue;
+                }
+                Err(e) => {
+                    let delay = backoff.next_delay(&e);
+                    error!("Failed to get {}: {}, retrying in {} seconds", self.config.ip_type, e, delay.as_secs());
+                    self.update_state(|state, key| state.record_error(key, &e));
+                    changed_at = self.wait_for_next_check(&mut monitor, delay).await;
                     continue;
                 }
             };
             
+            // 非公網地址（CGNAT、私有、bogon）依策略略過，不記錄為已發布
+            match self.should_publish(current_ip) {
+                Ok(true) => {}
+                Ok(false) => {
+                    backoff.reset();
+                    changed_at = self.wait_for_next_check(&mut monitor, interval).await;
+                    continue;
+                }
+                Err(e) => {
+                    error!("Refusing to update {} DDNS: {}, will check again in {} seconds", self.config.ip_type, e, self.config.update_interval);
+                    self.update_state(|state, key| state.record_error(key, &e));
+                    changed_at = self.wait_for_next_check(&mut monitor, interval).await;
+                    continue;
+                }
+            }
+            
             // 如果 IP 有變更，或者這是第一次檢查，更新 DNS 記錄
-            if last_ip.is_empty() {
-                info!("{} Initial check, current IP: {}", self.config.ip_type, current_ip);
-            } else if last_ip != current_ip {
-                info!("{} IP has changed from {} to {}", self.config.ip_type, last_ip, current_ip);
-            } else {
-                info!("{} unchanged ({}), skipping update, will check again in {} seconds", self.config.ip_type, current_ip, self.config.update_interval);
-                sleep(interval).await;
-                continue;
+            match last_ip {
+                None => info!("{} Initial check, current IP: {}", self.config.ip_type, current_ip),
+                Some(last) if last != current_ip => info!("{} IP has changed from {} to {}", self.config.ip_type, last, current_ip),
+                Some(_) => {
+                    if reconcile_pending || reconcile_interval.is_some_and(|reconcile| last_reconciled.elapsed() >= reconcile) {
+                        match self.reconcile(current_ip).await {
+                            Ok(_) => {
+                                last_reconciled = Instant::now();
+                                reconcile_pending = false;
+                            }
+                            Err(e) if ErrorClass::is_fatal(&e) => {
+                                self.stop_after(&e).await;
+                                return;
+                            }
+                            Err(e) => {
+                                let delay = backoff.next_delay(&e);
+                                error!("Failed to reconcile {} DDNS: {}, retrying in {} seconds", self.config.ip_type, e, delay.as_secs());
+                                changed_at = self.wait_for_next_check(&mut monitor, delay).await;
+                                continue;
+                            }
+                        }
+                    }
+                    backoff.reset();
+                    info!("{} unchanged ({}), skipping update, will check again in {} seconds", self.config.ip_type, current_ip, self.config.update_interval);
+                    changed_at = self.wait_for_next_check(&mut monitor, interval).await;
+                    continue;
+                }
             }
             
-            let update_result = self.update_record().await;
+            let update_result = self.update_record_with_ip(current_ip).await;
             
             match update_result {
                 Ok(result) => {
-                    info!("Successfully updated {} DDNS: {}, will check again in {} seconds", 
+                    info!("{} DDNS check succeeded: {}, will check again in {} seconds", 
                         self.config.ip_type,
                         serde_json::to_string(&result).unwrap_or_else(|_| format!("{:?}", result)),
                         self.config.update_interval
                     );
-                    last_ip = current_ip;
+                    last_ip = Some(current_ip);
+                    last_reconciled = Instant::now();
+                    reconcile_pending = false;
+                    backoff.reset();
+                }
+                Err(e) if ErrorClass::is_fatal(&e) => {
+                    self.stop_after(&e).await;
+                    return;
                 }
                 Err(e) => {
-                    error!("Failed to update {} DDNS: {}, retrying in 60 seconds", self.config.ip_type, e);
-                    sleep(Duration::from_secs(60)).await;
+                    let delay = backoff.next_delay(&e);
+                    error!(
+                        "Failed to update {} DDNS: {}, retrying in {} seconds (attempt {})",
+                        self.config.ip_type, e, delay.as_secs(), backoff.failures()
+                    );
+                    changed_at = self.wait_for_next_check(&mut monitor, delay).await;
                     continue;
                 }
             }
             
             info!("{} DDNS update completed, entering sleep mode, will check again in {} seconds", self.config.ip_type, self.config.update_interval);
-            sleep(interval).await;
+            changed_at = self.wait_for_next_check(&mut monitor, interval).await;
         }
     }
-} 
\ No newline at end of file
+
+    /// 記錄無法重試的錯誤並說明服務已停止
+    /// 
+    /// 所有記錄都已在狀態中標記為失敗，此處將其寫回文件
+    /// 
+    /// # 參數
+    /// 
+    /// - `error`: 導致停止的錯誤
+    async fn stop_after(&self, error: &AppError) {
+        error!(
+            "Stopping {} DDNS for {}: {} cannot be fixed by retrying, correct the configuration and restart the service",
+            self.config.ip_type, self.describe_records(), error
+        );
+        self.flush_state().await;
+    }
+
+    /// 根據配置建立地址變更監聽器
+    /// 
+    /// # 返回
+    /// 
+    /// - `Option<AddressMonitor>`: 未啟用或建立失敗時返回 `None`，此時僅使用輪詢
+    fn create_address_monitor(&self) -> Option<AddressMonitor> {
+        if !self.config.watch_address_changes {
+            return None;
+        }
+        
+        match IpVersion::from_ip_type(&self.config.ip_type).and_then(AddressMonitor::new) {
+            Ok(monitor) => {
+                info!("{} watching local address changes", self.config.ip_type);
+                Some(monitor)
+            }
+            Err(e) => {
+                warn!("Unable to watch {} address changes, falling back to polling: {}", self.config.ip_type, e);
+                None
+            }
+        }
+    }
+
+    /// 等待下一次檢查
+    /// 
+    /// # 參數
+    /// 
+    /// - `monitor`: 地址變更監聽器，監聽失敗時會被停用
+    /// - `delay`: 最長等待時間
+    /// 
+    /// # 行為
+    /// 
+    /// 先將此次檢查的狀態變更寫回文件，再等待 `delay` 或本機地址變更（以先發生者為準）
+    /// 
+    /// # 返回
+    /// 
+    /// - `Option<Instant>`: 因本機地址變更而提前結束時返回變更發生的時間
+    async fn wait_for_next_check(&self, monitor: &mut Option<AddressMonitor>, delay: Duration) -> Option<Instant> {
+        self.flush_state().await;
+
+        let Some(active) = monitor.as_mut() else {
+            sleep(delay).await;
+            return None;
+        };
+        
+        let result = tokio::select! {
+            _ = sleep(delay) => Ok(None),
+            result = active.changed() => result.map(|changed_at| {
+                info!("{} local address changed, checking immediately", self.config.ip_type);
+                Some(changed_at)
+            }),
+        };
+        
+        match result {
+            Ok(changed_at) => changed_at,
+            Err(e) => {
+                warn!("Stopped watching {} address changes, falling back to polling: {}", self.config.ip_type, e);
+                *monitor = None;
+                sleep(delay).await;
+                None
+            }
+        }
+    }
+}
+
+/// 依 HTTP 狀態與錯誤代碼（包括錯誤鏈）將 Cloudflare API 錯誤轉換為應用程式錯誤
+/// 
+/// # 參數
+/// 
+/// - `status`: HTTP 狀態
+/// - `errors`: 響應中的錯誤列表
+/// 
+/// # 返回
+/// 
+/// - `AppError`: 依序判斷為 `RateLimited`（429）、`AuthenticationError`（401 / 403）、`NotFound`（404）、
+///   `ValidationError`（400 / 409 / 422），其他情況為 `ExternalServiceError`
+fn classify_api_error(status: StatusCode, errors: &[CloudflareError]) -> AppError {
+    let has_code = |matches: &dyn Fn(u32) -> bool| errors.iter().any(|e| e.has_code(&matches));
+    let message = if errors.is_empty() {
+        format!("Cloudflare API error (HTTP {})", status)
+    } else {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        format!("Cloudflare API error (HTTP {}): {}", status, errors.join("; "))
+    };
+
+    if status == StatusCode::TOO_MANY_REQUESTS || has_code(&|code| RATE_LIMIT_ERROR_CODES.contains(&code)) {
+        AppError::RateLimited(message)
+    } else if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+        || has_code(&|code| AUTH_ERROR_CODES.contains(&code))
+    {
+        AppError::AuthenticationError(message)
+    } else if status == StatusCode::NOT_FOUND || has_code(&|code| NOT_FOUND_ERROR_CODES.contains(&code)) {
+        AppError::NotFound(message)
+    } else if matches!(status, StatusCode::BAD_REQUEST | StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY)
+        || has_code(&|code| VALIDATION_ERROR_CODES.contains(&code) || (9000..10000).contains(&code))
+    {
+        AppError::ValidationError(message)
+    } else {
+        AppError::ExternalServiceError(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析 Cloudflare 響應中的 `errors`
+    fn errors(errors: serde_json::Value) -> Vec<CloudflareError> {
+        serde_json::from_value(errors).unwrap()
+    }
+
+    #[test]
+    fn codes_take_precedence_over_bad_request() {
+        let error = classify_api_error(StatusCode::BAD_REQUEST, &errors(serde_json::json!([{ "code": 7003, "message": "Could not route to /zones/x/dns_records/y" }])));
+        assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+
+        let error = classify_api_error(StatusCode::BAD_REQUEST, &errors(serde_json::json!([{ "code": 9109, "message": "Invalid access token" }])));
+        assert!(matches!(error, AppError::AuthenticationError(_)), "{:?}", error);
+
+        let error = classify_api_error(StatusCode::OK, &errors(serde_json::json!([{ "code": 971, "message": "Please wait and consider throttling your request speed" }])));
+        assert!(matches!(error, AppError::RateLimited(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn classifies_validation_errors() {
+        let error = classify_api_error(StatusCode::CONFLICT, &[]);
+        assert!(matches!(error, AppError::ValidationError(_)), "{:?}", error);
+
+        let error = classify_api_error(StatusCode::OK, &errors(serde_json::json!([{ "code": 81057, "message": "Record already exists." }])));
+        assert!(matches!(error, AppError::ValidationError(_)), "{:?}", error);
+
+        let error = classify_api_error(StatusCode::OK, &errors(serde_json::json!([{ "code": 9005, "message": "Content for A record is invalid." }])));
+        assert!(matches!(error, AppError::ValidationError(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn classifies_statuses_without_body() {
+        let error = classify_api_error(StatusCode::BAD_GATEWAY, &[]);
+        assert!(matches!(&error, AppError::ExternalServiceError(message) if message == "Cloudflare API error (HTTP 502 Bad Gateway)"), "{:?}", error);
+
+        assert!(matches!(classify_api_error(StatusCode::SERVICE_UNAVAILABLE, &[]), AppError::ExternalServiceError(_)));
+        assert!(matches!(classify_api_error(StatusCode::TOO_MANY_REQUESTS, &[]), AppError::RateLimited(_)));
+        assert!(matches!(classify_api_error(StatusCode::FORBIDDEN, &[]), AppError::AuthenticationError(_)));
+        assert!(matches!(classify_api_error(StatusCode::NOT_FOUND, &[]), AppError::NotFound(_)));
+    }
+
+    #[test]
+    fn matches_codes_in_error_chain() {
+        let chain = errors(serde_json::json!([{
+            "code": 1000,
+            "message": "Request failed",
+            "error_chain": [{ "code": 1001, "message": "Upstream failure", "error_chain": [{ "code": 10000, "message": "Authentication error" }] }]
+        }]));
+        let error = classify_api_error(StatusCode::INTERNAL_SERVER_ERROR, &chain);
+        assert!(matches!(&error, AppError::AuthenticationError(message)
+            if message.ends_with("[1000] Request failed: [1001] Upstream failure: [10000] Authentication error")), "{:?}", error);
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use crate::config::settings::HttpSettings;
+use crate::error::AppError;
+use log::{info, warn};
+
+/// 全域 HTTP 客戶端設置（由 `config/default.toml` 的 `[http]` 區段設置）
+static SETTINGS: OnceLock<HttpSettings> = OnceLock::new();
+/// 共用的 HTTP 客戶端
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+/// 共用的不經代理 HTTP 客戶端
+static DIRECT_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+/// 依本機地址綁定的 HTTP 客戶端
+static BOUND_CLIENTS: OnceLock<Mutex<HashMap<IpAddr, reqwest::Client>>> = OnceLock::new();
+
+/// 設置全域 HTTP 客戶端
+///
+/// 只在第一次呼叫時生效，應於啟動時、建立任何 IP 來源或 DDNS 服務之前呼叫
+///
+/// # 參數
+///
+/// - `settings`: HTTP 客戶端設置
+///
+/// # 返回
+///
+/// - `Result<(), AppError>`: 設置無效（例如代理地址錯誤）時返回錯誤
+pub fn configure(settings: HttpSettings) -> Result<(), AppError> {
+    let client = build(&settings, true)?;
+    let direct = build(&settings, false)?;
+    let proxy = settings.proxy.clone();
+
+    if SETTINGS.set(settings).is_err() {
+        warn!("HTTP client already configured, ignoring new configuration");
+        return Ok(());
+    }
+    if let Some(proxy) = proxy {
+        info!("Outbound HTTP requests use proxy {}", proxy);
+        // HTTP IP 來源經由代理查詢時無法綁定本機地址族，只能檢查返回地址的版本
+        warn!(
+            "HTTP IP sources observe the egress address of proxy {} and cannot be limited to IPv4 or IPv6; \
+             use dns, stun or interface sources to detect the host's own addresses",
+            proxy
+        );
+    }
+    let _ = CLIENT.set(client);
+    let _ = DIRECT_CLIENT.set(direct);
+
+    Ok(())
+}
+
+/// 取得全域 HTTP 客戶端設置，未設置時使用預設值
+pub fn settings() -> &'static HttpSettings {
+    SETTINGS.get_or_init(HttpSettings::default)
+}
+
+/// 取得共用的 HTTP 客戶端
+///
+/// 客戶端內部以引用計數共用連線池，複製的成本很低
+pub fn client() -> reqwest::Client {
+    CLIENT.get_or_init(|| build_or_fallback(true)).clone()
+}
+
+/// 取得不經過代理的 HTTP 客戶端，用於存取區域網路內的裝置（例如路由器）
+pub fn direct_client() -> reqwest::Client {
+    DIRECT_CLIENT.get_or_init(|| build_or_fallback(false)).clone()
+}
+
+/// 取得綁定到指定本機地址的 HTTP 客戶端
+///
+/// 綁定到 `0.0.0.0` 或 `::` 時只會連線到該地址族的目標地址，
+/// 用於確保 IPv4 / IPv6 查詢走對應的網路路徑
+///
+/// # 參數
+///
+/// - `local_address`: 出站連線綁定的本機地址（取代設置中的 `local_address`）
+pub fn bound_client(local_address: IpAddr) -> reqwest::Client {
+    let clients = BOUND_CLIENTS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut clients = clients.lock().unwrap_or_else(|e| e.into_inner());
+
+    clients
+        .entry(local_address)
+        .or_insert_with(|| {
+            build_with(settings(), true, Some(local_address)).unwrap_or_else(|e| {
+                warn!("{}, using HTTP client without proxy bound to {}", e, local_address);
+                fallback_client(settings(), Some(local_address))
+            })
+        })
+        .clone()
+}
+
+/// 根據設置建立 HTTP 客戶端
+///
+/// # 參數
+///
+/// - `settings`: HTTP 客戶端設置
+/// - `use_proxy`: 是否使用設置中的代理
+///
+/// # 返回
+///
+/// - `Result<reqwest::Client, AppError>`: 成功時返回客戶端，失敗時返回錯誤
+pub fn build(settings: &HttpSettings, use_proxy: bool) -> Result<reqwest::Client, AppError> {
+    build_with(settings, use_proxy, settings.local_address)
+}
+
+/// 根據設置與指定的本機地址建立 HTTP 客戶端
+fn build_with(settings: &HttpSettings, use_proxy: bool, local_address: Option<IpAddr>) -> Result<reqwest::Client, AppError> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout))
+        .timeout(Duration::from_secs(settings.timeout))
+        .user_agent(settings.user_agent.as_str())
+        .local_address(local_address);
+
+    match (&settings.proxy, use_proxy) {
+        (Some(proxy), true) => {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| AppError::ConfigError(format!("Invalid proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        // 未設置代理時仍遵循 HTTP_PROXY / HTTPS_PROXY 環境變量；直連客戶端則完全不使用代理
+        (_, false) => builder = builder.no_proxy(),
+        (None, true) => {}
+    }
+
+    builder
+        .build()
+        .map_err(|e| AppError::ConfigError(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// 使用目前的設置建立客戶端，失敗時退回不使用代理的客戶端
+fn build_or_fallback(use_proxy: bool) -> reqwest::Client {
+    build(settings(), use_proxy).unwrap_or_else(|e| {
+        warn!("{}, using HTTP client without proxy", e);
+        fallback_client(settings(), settings().local_address)
+    })
+}
+
+/// 設置無法建立客戶端時使用的客戶端
+///
+/// 不使用代理，但保留設置中的逾時與 User-Agent；這些設置本身無效時使用預設值，確保請求不會無限等待
+///
+/// # 參數
+///
+/// - `settings`: HTTP 客戶端設置
+/// - `local_address`: 出站連線綁定的本機地址（可選）
+fn fallback_client(settings: &HttpSettings, local_address: Option<IpAddr>) -> reqwest::Client {
+    build_with(settings, false, local_address)
+        .or_else(|_| build_with(&HttpSettings::default(), false, local_address))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    /// 啟動只接受一個連線的 TCP 伺服器
+    ///
+    /// # 參數
+    ///
+    /// - `respond`: 是否回應；為 `false` 時保持連線但不回應
+    ///
+    /// # 返回
+    ///
+    /// - 監聽地址，以及收到的請求標頭
+    async fn start_server(respond: bool) -> (SocketAddr, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0u8; 4096];
+            let read = stream.read(&mut buffer).await.unwrap();
+            let _ = sender.send(String::from_utf8_lossy(&buffer[..read]).into_owned());
+            if respond {
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await;
+            } else {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+        });
+        (address, receiver)
+    }
+
+    fn settings(timeout: u64) -> HttpSettings {
+        HttpSettings { timeout, ..HttpSettings::default() }
+    }
+
+    #[tokio::test]
+    async fn sends_configured_user_agent() {
+        let (address, request) = start_server(true).await;
+        let client = build(&HttpSettings { user_agent: "ddns-test/1.0".to_string(), ..settings(5) }, true).unwrap();
+
+        client.get(format!("http://{}/", address)).send().await.unwrap();
+        let request = request.await.unwrap().to_ascii_lowercase();
+        assert!(request.contains("user-agent: ddns-test/1.0"), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn applies_request_timeout() {
+        let (address, _request) = start_server(false).await;
+        let client = build(&settings(1), true).unwrap();
+
+        let started = std::time::Instant::now();
+        let error = client.get(format!("http://{}/", address)).send().await.unwrap_err();
+        assert!(error.is_timeout(), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn routes_requests_through_proxy_unless_direct() {
+        let (proxy, request) = start_server(true).await;
+        let settings = HttpSettings { proxy: Some(format!("http://{}", proxy)), ..settings(5) };
+
+        let client = build(&settings, true).unwrap();
+        client.get("http://ddns.invalid/ip").send().await.unwrap();
+        let request = request.await.unwrap();
+        assert!(request.starts_with("GET http://ddns.invalid/ip HTTP/1.1"), "{}", request);
+
+        // 直連客戶端不使用代理，無法解析的主機名直接失敗
+        let direct = build(&settings, false).unwrap();
+        assert!(direct.get("http://ddns.invalid/ip").send().await.is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_proxy() {
+        let settings = HttpSettings { proxy: Some("not a proxy".to_string()), ..settings(5) };
+        assert!(matches!(build(&settings, true), Err(AppError::ConfigError(_))));
+        assert!(build(&settings, false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn fallback_keeps_timeouts() {
+        let (address, _request) = start_server(false).await;
+        let settings = HttpSettings { proxy: Some("not a proxy".to_string()), ..settings(1) };
+        let client = fallback_client(&settings, Some("127.0.0.1".parse().unwrap()));
+
+        let error = client.get(format!("http://{}/", address)).send().await.unwrap_err();
+        assert!(error.is_timeout(), "{}", error);
+    }
+}
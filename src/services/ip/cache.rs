@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use crate::error::AppError;
+use super::{build_detector, IpSourceConfig, IpVersion};
+use log::debug;
+
+/// 快取鍵：來源配置（序列化後）、共識設置與 IP 版本
+type CacheKey = (String, Option<usize>, IpVersion);
+
+/// 進行中的查詢，所有等待者共用同一個結果（包括錯誤）
+type Flight = Shared<BoxFuture<'static, Result<IpAddr, AppError>>>;
+
+/// 一次查詢結果
+///
+/// # 欄位
+///
+/// - `ip`: 查詢到的地址
+/// - `observed_at`: 開始查詢的時間
+struct Observation {
+    ip: IpAddr,
+    observed_at: Instant,
+}
+
+/// 快取鍵對應的狀態
+///
+/// # 欄位
+///
+/// - `cached`: 最近一次成功的查詢結果（可選）
+/// - `in_flight`: 進行中的查詢及其開始時間（可選）
+#[derive(Default)]
+struct Slot {
+    cached: Option<Observation>,
+    in_flight: Option<(Instant, Flight)>,
+}
+
+/// 共用的 IP 觀察者
+///
+/// 以來源配置、共識設置與 IP 版本為鍵快取查詢結果。同一個鍵同時只會有一個查詢在進行，
+/// 其他呼叫者加入該查詢並共用其結果（包括錯誤），因此多筆記錄與 API 請求不會重複查詢回顯服務。
+/// 查詢失敗不會被快取
+///
+/// # 欄位
+///
+/// - `ttl`: 快取結果的有效時間，0 表示只合併同時進行的查詢
+/// - `slots`: 每個快取鍵的快取結果與進行中的查詢
+pub struct IpObserver {
+    ttl: Duration,
+    slots: Mutex<HashMap<CacheKey, Slot>>,
+}
+
+impl IpObserver {
+    /// 創建新的 IP 觀察者
+    ///
+    /// # 參數
+    ///
+    /// - `ttl`: 快取結果的有效時間
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 取得目前的 IP 地址，快取有效時直接返回快取結果
+    ///
+    /// # 參數
+    ///
+    /// - `sources`: 來源配置
+    /// - `quorum`: 共識所需的一致來源數（可選）
+    /// - `version`: IP 版本
+    /// - `changed_since`: 已知地址在此時間點發生變更，早於此時間的快取結果與查詢視為過期（可選）
+    ///
+    /// # 返回
+    ///
+    /// - `Result<IpAddr, AppError>`: 成功時返回 IP 地址，失敗時返回錯誤
+    pub async fn observe(
+        &self,
+        sources: &[IpSourceConfig],
+        quorum: Option<usize>,
+        version: IpVersion,
+        changed_since: Option<Instant>,
+    ) -> Result<IpAddr, AppError> {
+        let key = (
+            serde_json::to_string(sources).map_err(|e| AppError::InternalError(e.to_string()))?,
+            quorum,
+            version,
+        );
+        let current = |started_at: Instant| changed_since.is_none_or(|changed| started_at >= changed);
+
+        let (started_at, flight) = {
+            let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = slots.entry(key.clone()).or_default();
+
+            if let Some(observation) = slot.cached.as_ref()
+                && observation.observed_at.elapsed() < self.ttl
+                && current(observation.observed_at)
+            {
+                debug!("Using cached {} address {}", version, observation.ip);
+                return Ok(observation.ip);
+            }
+
+            match slot.in_flight.as_ref().filter(|(started_at, _)| current(*started_at)) {
+                Some((started_at, flight)) => {
+                    debug!("Joining in-flight {} address lookup", version);
+                    (*started_at, flight.clone())
+                }
+                None => {
+                    let detector = build_detector(sources, quorum)?;
+                    let flight = async move { detector.fetch(version).await }.boxed().shared();
+                    let started_at = Instant::now();
+                    slot.in_flight = Some((started_at, flight.clone()));
+                    (started_at, flight)
+                }
+            }
+        };
+
+        let result = flight.clone().await;
+
+        // 第一個完成的等待者清除進行中的查詢，成功時寫入快取
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(slot) = slots.get_mut(&key)
+            && slot.in_flight.as_ref().is_some_and(|(_, in_flight)| in_flight.ptr_eq(&flight))
+        {
+            slot.in_flight = None;
+            if let Ok(ip) = result
+                && slot.cached.as_ref().is_none_or(|cached| cached.observed_at < started_at)
+            {
+                slot.cached = Some(Observation { ip, observed_at: started_at });
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 啟動延遲回應的 HTTP IP 來源，返回來源配置與請求計數
+    async fn slow_source(status: &'static str, body: &'static str) -> (Vec<IpSourceConfig>, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buffer = [0u8; 1024];
+                    let _ = stream.read(&mut buffer).await;
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (vec![IpSourceConfig::parse(&url).unwrap()], hits)
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_merges_concurrent_lookups() {
+        let (sources, hits) = slow_source("200 OK", "198.51.100.7").await;
+        let observer = IpObserver::new(Duration::ZERO);
+
+        let (first, second) = tokio::join!(
+            observer.observe(&sources, None, IpVersion::V4, None),
+            observer.observe(&sources, None, IpVersion::V4, None),
+        );
+        assert_eq!(first.unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+        assert_eq!(second.unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // ttl 為 0 時，查詢完成後的呼叫重新查詢
+        observer.observe(&sources, None, IpVersion::V4, None).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn errors_are_shared_but_not_cached() {
+        let (sources, hits) = slow_source("500 Internal Server Error", "").await;
+        let observer = IpObserver::new(Duration::from_secs(60));
+
+        let (first, second) = tokio::join!(
+            observer.observe(&sources, None, IpVersion::V4, None),
+            observer.observe(&sources, None, IpVersion::V4, None),
+        );
+        assert!(first.is_err());
+        assert!(second.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        assert!(observer.observe(&sources, None, IpVersion::V4, None).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn cached_result_is_reused_until_address_changes() {
+        let (sources, hits) = slow_source("200 OK", "198.51.100.7").await;
+        let observer = IpObserver::new(Duration::from_secs(60));
+
+        observer.observe(&sources, None, IpVersion::V4, None).await.unwrap();
+        observer.observe(&sources, None, IpVersion::V4, None).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // 地址在快取結果之後變更時重新查詢
+        observer.observe(&sources, None, IpVersion::V4, Some(Instant::now())).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+}
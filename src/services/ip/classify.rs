@@ -0,0 +1,247 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use serde::{Deserialize, Serialize};
+
+/// IP 地址分類
+///
+/// # 變體
+///
+/// - `Public`: 可公開路由的地址
+/// - `Cgnat`: 電信級 NAT 共享地址（100.64.0.0/10）
+/// - `Private`: 私有地址（RFC 1918、IPv6 ULA fc00::/7）
+/// - `Loopback`: 回環地址
+/// - `LinkLocal`: 鏈路本地地址
+/// - `Bogon`: 其他不應出現在公網的地址（保留、文件、基準測試、多播等範圍）
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressClass {
+    Public,
+    Cgnat,
+    Private,
+    Loopback,
+    LinkLocal,
+    Bogon,
+}
+
+impl AddressClass {
+    /// 是否為可公開路由的地址
+    pub fn is_public(self) -> bool {
+        self == AddressClass::Public
+    }
+}
+
+impl fmt::Display for AddressClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressClass::Public => write!(f, "public"),
+            AddressClass::Cgnat => write!(f, "CGNAT shared address space"),
+            AddressClass::Private => write!(f, "private"),
+            AddressClass::Loopback => write!(f, "loopback"),
+            AddressClass::LinkLocal => write!(f, "link-local"),
+            AddressClass::Bogon => write!(f, "bogon"),
+        }
+    }
+}
+
+/// 偵測到非公網地址時的處理策略
+///
+/// # 變體
+///
+/// - `Warn`: 記錄警告後照常更新
+/// - `Skip`: 記錄警告並略過此次更新
+/// - `Fail`: 視為錯誤
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NonPublicPolicy {
+    #[default]
+    Warn,
+    Skip,
+    Fail,
+}
+
+impl NonPublicPolicy {
+    /// 從字串解析策略
+    ///
+    /// # 參數
+    ///
+    /// - `value`: 策略名稱（"warn"、"skip" 或 "fail"）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "warn" => Some(NonPublicPolicy::Warn),
+            "skip" => Some(NonPublicPolicy::Skip),
+            "fail" => Some(NonPublicPolicy::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// 分類 IP 地址
+///
+/// # 參數
+///
+/// - `ip`: 要分類的地址
+pub fn classify(ip: IpAddr) -> AddressClass {
+    match ip {
+        IpAddr::V4(ip) => classify_v4(ip),
+        IpAddr::V6(ip) => classify_v6(ip),
+    }
+}
+
+/// IPv4 不可公開路由的範圍（Cgnat、Private、Loopback、LinkLocal 以外）
+const V4_BOGONS: [([u8; 4], u8); 9] = [
+    ([0, 0, 0, 0], 8),        // "this" network
+    ([192, 0, 0, 0], 24),     // IETF 協定分配
+    ([192, 0, 2, 0], 24),     // TEST-NET-1
+    ([198, 18, 0, 0], 15),    // 基準測試
+    ([198, 51, 100, 0], 24),  // TEST-NET-2
+    ([203, 0, 113, 0], 24),   // TEST-NET-3
+    ([224, 0, 0, 0], 4),      // 多播
+    ([240, 0, 0, 0], 4),      // 保留（含廣播地址）
+    ([192, 88, 99, 0], 24),   // 已棄用的 6to4 中繼任播
+];
+
+/// `192.0.0.0/24` 中可全域路由的地址（PCP 任播、TURN 中繼任播，RFC 7723 / RFC 8155）
+const V4_GLOBAL_IN_BOGONS: [Ipv4Addr; 2] = [Ipv4Addr::new(192, 0, 0, 9), Ipv4Addr::new(192, 0, 0, 10)];
+
+fn classify_v4(ip: Ipv4Addr) -> AddressClass {
+    if in_v4_range(ip, [100, 64, 0, 0], 10) {
+        AddressClass::Cgnat
+    } else if ip.is_private() {
+        AddressClass::Private
+    } else if ip.is_loopback() {
+        AddressClass::Loopback
+    } else if ip.is_link_local() {
+        AddressClass::LinkLocal
+    } else if V4_BOGONS.iter().any(|(net, len)| in_v4_range(ip, *net, *len)) && !V4_GLOBAL_IN_BOGONS.contains(&ip) {
+        AddressClass::Bogon
+    } else {
+        AddressClass::Public
+    }
+}
+
+fn classify_v6(ip: Ipv6Addr) -> AddressClass {
+    // IPv4 映射地址依其 IPv4 地址分類
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return classify_v4(v4);
+    }
+
+    let segments = ip.segments();
+    if ip.is_loopback() {
+        AddressClass::Loopback
+    } else if ip.is_unicast_link_local() {
+        AddressClass::LinkLocal
+    } else if ip.is_unique_local() {
+        AddressClass::Private
+    } else if segments[0] & 0xe000 != 0x2000
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+    {
+        // 只有 2000::/3 為全域單播，2001:db8::/32 為文件用途
+        AddressClass::Bogon
+    } else {
+        AddressClass::Public
+    }
+}
+
+fn in_v4_range(ip: Ipv4Addr, network: [u8; 4], prefix_len: u8) -> bool {
+    let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+    u32::from(ip) & mask == u32::from(Ipv4Addr::from(network)) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(ip: &str) -> AddressClass {
+        classify(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn cgnat_range_boundaries() {
+        assert_eq!(class("100.63.255.255"), AddressClass::Public);
+        assert_eq!(class("100.64.0.0"), AddressClass::Cgnat);
+        assert_eq!(class("100.127.255.255"), AddressClass::Cgnat);
+        assert_eq!(class("100.128.0.0"), AddressClass::Public);
+    }
+
+    #[test]
+    fn ipv4_bogon_boundaries() {
+        assert_eq!(class("192.0.1.255"), AddressClass::Public);
+        assert_eq!(class("192.0.2.0"), AddressClass::Bogon);
+        assert_eq!(class("192.0.2.255"), AddressClass::Bogon);
+        assert_eq!(class("192.0.3.0"), AddressClass::Public);
+
+        assert_eq!(class("198.17.255.255"), AddressClass::Public);
+        assert_eq!(class("198.18.0.0"), AddressClass::Bogon);
+        assert_eq!(class("198.19.255.255"), AddressClass::Bogon);
+        assert_eq!(class("198.20.0.0"), AddressClass::Public);
+
+        assert_eq!(class("223.255.255.255"), AddressClass::Public);
+        assert_eq!(class("224.0.0.1"), AddressClass::Bogon);
+        assert_eq!(class("255.255.255.255"), AddressClass::Bogon);
+        assert_eq!(class("0.0.0.0"), AddressClass::Bogon);
+    }
+
+    #[test]
+    fn ietf_protocol_assignments_keep_global_anycast() {
+        assert_eq!(class("191.255.255.255"), AddressClass::Public);
+        assert_eq!(class("192.0.0.0"), AddressClass::Bogon);
+        assert_eq!(class("192.0.0.8"), AddressClass::Bogon);
+        assert_eq!(class("192.0.0.9"), AddressClass::Public);
+        assert_eq!(class("192.0.0.10"), AddressClass::Public);
+        assert_eq!(class("192.0.0.11"), AddressClass::Bogon);
+        assert_eq!(class("192.0.0.255"), AddressClass::Bogon);
+        assert_eq!(class("192.0.1.0"), AddressClass::Public);
+        assert_eq!(class("::ffff:192.0.0.9"), AddressClass::Public);
+    }
+
+    #[test]
+    fn ipv4_local_ranges() {
+        assert_eq!(class("10.0.0.1"), AddressClass::Private);
+        assert_eq!(class("172.16.0.1"), AddressClass::Private);
+        assert_eq!(class("172.32.0.1"), AddressClass::Public);
+        assert_eq!(class("192.168.1.1"), AddressClass::Private);
+        assert_eq!(class("127.0.0.1"), AddressClass::Loopback);
+        assert_eq!(class("169.254.1.1"), AddressClass::LinkLocal);
+        assert_eq!(class("1.1.1.1"), AddressClass::Public);
+    }
+
+    #[test]
+    fn ipv6_documentation_and_private_ranges() {
+        assert_eq!(class("2001:db7:ffff:ffff:ffff:ffff:ffff:ffff"), AddressClass::Public);
+        assert_eq!(class("2001:db8::"), AddressClass::Bogon);
+        assert_eq!(class("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"), AddressClass::Bogon);
+        assert_eq!(class("2001:db9::"), AddressClass::Public);
+
+        assert_eq!(class("fbff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), AddressClass::Bogon);
+        assert_eq!(class("fc00::"), AddressClass::Private);
+        assert_eq!(class("fdff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), AddressClass::Private);
+        assert_eq!(class("fe80::1"), AddressClass::LinkLocal);
+        assert_eq!(class("::1"), AddressClass::Loopback);
+    }
+
+    #[test]
+    fn ipv6_global_unicast_boundaries() {
+        assert_eq!(class("1fff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), AddressClass::Bogon);
+        assert_eq!(class("2000::"), AddressClass::Public);
+        assert_eq!(class("3fff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), AddressClass::Public);
+        assert_eq!(class("4000::"), AddressClass::Bogon);
+        assert_eq!(class("ff02::1"), AddressClass::Bogon);
+        assert_eq!(class("::"), AddressClass::Bogon);
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_use_ipv4_class() {
+        assert_eq!(class("::ffff:10.0.0.1"), AddressClass::Private);
+        assert_eq!(class("::ffff:100.64.0.1"), AddressClass::Cgnat);
+        assert_eq!(class("::ffff:1.1.1.1"), AddressClass::Public);
+    }
+
+    #[test]
+    fn parses_policy() {
+        assert_eq!(NonPublicPolicy::parse(" warn "), Some(NonPublicPolicy::Warn));
+        assert_eq!(NonPublicPolicy::parse("fail"), Some(NonPublicPolicy::Fail));
+        assert_eq!(NonPublicPolicy::parse("ignore"), None);
+        assert_eq!(NonPublicPolicy::parse("skip"), Some(NonPublicPolicy::Skip));
+        assert_eq!(NonPublicPolicy::default(), NonPublicPolicy::Warn);
+    }
+}
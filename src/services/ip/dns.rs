@@ -0,0 +1,445 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::time::timeout;
+use crate::error::AppError;
+use super::{family, IpVersion, parse_ip};
+use super::source::IpSource;
+
+/// DNS 查詢逾時
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// DNS 預設端口
+const DNS_PORT: u16 = 53;
+/// UDP DNS 響應的最大長度
+const MAX_RESPONSE_LEN: usize = 4096;
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+
+/// DNS 查詢的記錄類型
+///
+/// # 變體
+///
+/// - `Address`: 查詢 A（IPv4）或 AAAA（IPv6）記錄
+/// - `Txt`: 查詢 TXT 記錄，內容為 IP 地址
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsRecordType {
+    #[default]
+    Address,
+    Txt,
+}
+
+/// DNS 查詢類別
+///
+/// # 變體
+///
+/// - `In`: Internet 類別
+/// - `Ch`: CHAOS 類別（例如 `whoami.cloudflare`）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsClass {
+    #[default]
+    In,
+    Ch,
+}
+
+impl DnsClass {
+    fn code(self) -> u16 {
+        match self {
+            DnsClass::In => 1,
+            DnsClass::Ch => 3,
+        }
+    }
+}
+
+/// 透過 DNS 查詢公網 IP 的來源
+///
+/// 向指定的解析器查詢特殊名稱（如 `myip.opendns.com`），由解析器回應其看到的來源地址。
+/// 在 HTTP 出站被代理或過濾的網路中仍然可用
+///
+/// # 欄位
+///
+/// - `name`: 來源名稱
+/// - `query_name`: 查詢的網域名稱
+/// - `record_type`: 查詢的記錄類型
+/// - `class`: 查詢類別
+/// - `ipv4_resolver`: IPv4 查詢使用的解析器（可選）
+/// - `ipv6_resolver`: IPv6 查詢使用的解析器（可選）
+pub struct DnsSource {
+    name: String,
+    query_name: String,
+    record_type: DnsRecordType,
+    class: DnsClass,
+    ipv4_resolver: Option<SocketAddr>,
+    ipv6_resolver: Option<SocketAddr>,
+}
+
+impl DnsSource {
+    /// 創建新的 DNS 來源
+    ///
+    /// # 參數
+    ///
+    /// - `name`: 來源名稱
+    /// - `query_name`: 查詢的網域名稱
+    /// - `record_type`: 查詢的記錄類型
+    /// - `class`: 查詢類別
+    /// - `ipv4_resolver`: IPv4 查詢使用的解析器（可選）
+    /// - `ipv6_resolver`: IPv6 查詢使用的解析器（可選）
+    pub fn new(
+        name: &str,
+        query_name: &str,
+        record_type: DnsRecordType,
+        class: DnsClass,
+        ipv4_resolver: Option<SocketAddr>,
+        ipv6_resolver: Option<SocketAddr>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            query_name: query_name.trim_end_matches('.').to_string(),
+            record_type,
+            class,
+            ipv4_resolver,
+            ipv6_resolver,
+        }
+    }
+
+    /// OpenDNS：向 resolver1.opendns.com 查詢 `myip.opendns.com` 的 A/AAAA 記錄
+    pub fn opendns() -> Self {
+        Self::new(
+            "opendns",
+            "myip.opendns.com",
+            DnsRecordType::Address,
+            DnsClass::In,
+            Some(SocketAddr::from(([208, 67, 222, 222], DNS_PORT))),
+            Some(SocketAddr::from(([0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35], DNS_PORT))),
+        )
+    }
+
+    /// Cloudflare：向 1.1.1.1 查詢 CHAOS 類別的 `whoami.cloudflare` TXT 記錄
+    pub fn cloudflare() -> Self {
+        Self::new(
+            "cloudflare_dns",
+            "whoami.cloudflare",
+            DnsRecordType::Txt,
+            DnsClass::Ch,
+            Some(SocketAddr::from(([1, 1, 1, 1], DNS_PORT))),
+            Some(SocketAddr::from(([0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111], DNS_PORT))),
+        )
+    }
+
+    /// 構建 DNS 查詢封包
+    fn build_query(&self, id: u16, qtype: u16) -> Result<Vec<u8>, AppError> {
+        let mut packet = Vec::with_capacity(32 + self.query_name.len());
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&0x0100u16.to_be_bytes()); // RD
+        packet.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0]); // ANCOUNT, NSCOUNT, ARCOUNT
+
+        for label in self.query_name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(AppError::ConfigError(format!("Invalid DNS name: {}", self.query_name)));
+            }
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&self.class.code().to_be_bytes());
+        Ok(packet)
+    }
+}
+
+#[async_trait]
+impl IpSource for DnsSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        let resolver = match version {
+            IpVersion::V4 => self.ipv4_resolver,
+            IpVersion::V6 => self.ipv6_resolver,
+        }
+        .ok_or_else(|| AppError::ConfigError(format!("{} has no resolver for {}", self.name, version)))?;
+
+        let qtype = match (self.record_type, version) {
+            (DnsRecordType::Txt, _) => TYPE_TXT,
+            (DnsRecordType::Address, IpVersion::V4) => TYPE_A,
+            (DnsRecordType::Address, IpVersion::V6) => TYPE_AAAA,
+        };
+
+        let id: u16 = rand::random();
+        let query = self.build_query(id, qtype)?;
+
+        let socket = family::bind_udp(version).await?;
+        socket.connect(resolver)
+            .await
+            .map_err(|e| AppError::AddressFamilyUnavailable(format!("cannot reach {}: {}", resolver, e)))?;
+        socket.send(&query)
+            .await
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to query {}: {}", resolver, e)))?;
+
+        let mut buf = vec![0u8; MAX_RESPONSE_LEN];
+        let answers = timeout(QUERY_TIMEOUT, async {
+            loop {
+                let len = socket.recv(&mut buf)
+                    .await
+                    .map_err(|e| AppError::ExternalServiceError(format!("Failed to receive from {}: {}", resolver, e)))?;
+                // 忽略 ID 不符的封包
+                if let Some(answers) = parse_response(&buf[..len], id, qtype)? {
+                    return Ok::<_, AppError>(answers);
+                }
+            }
+        })
+        .await
+        .map_err(|_| AppError::ExternalServiceError(format!("DNS query to {} timed out", resolver)))??;
+
+        let mut last_error = None;
+        for answer in &answers {
+            match parse_ip(answer, version) {
+                Ok(ip) => return Ok(ip),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| AppError::InvalidIpResponse(format!(
+            "no answer for {} from {}", self.query_name, resolver
+        ))))
+    }
+}
+
+/// 將解析器地址字串解析為 socket 地址，未指定端口時使用 53
+///
+/// # 參數
+///
+/// - `resolver`: 解析器地址（`1.1.1.1`、`1.1.1.1:53`、`2606:4700:4700::1111` 或 `[::1]:5353`）
+pub fn parse_resolver(resolver: &str) -> Result<SocketAddr, AppError> {
+    let resolver = resolver.trim();
+    resolver
+        .parse::<SocketAddr>()
+        .or_else(|_| resolver.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, DNS_PORT)))
+        .map_err(|_| AppError::ConfigError(format!("Invalid DNS resolver address: {}", resolver)))
+}
+
+/// 解析 DNS 響應
+///
+/// # 參數
+///
+/// - `packet`: 響應封包
+/// - `id`: 查詢 ID
+/// - `qtype`: 查詢的記錄類型
+///
+/// # 返回
+///
+/// - `Result<Option<Vec<String>>, AppError>`: ID 不符時返回 `None`，否則返回答案內容（A/AAAA 轉為文字，TXT 為字串內容）
+fn parse_response(packet: &[u8], id: u16, qtype: u16) -> Result<Option<Vec<String>>, AppError> {
+    let malformed = || AppError::InvalidIpResponse("malformed DNS response".to_string());
+
+    if packet.len() < 12 {
+        return Err(malformed());
+    }
+    if u16::from_be_bytes([packet[0], packet[1]]) != id {
+        return Ok(None);
+    }
+
+    let flags = u16::from_be_bytes([packet[2], packet[3]]);
+    if flags & 0x8000 == 0 {
+        return Ok(None);
+    }
+    let rcode = flags & 0x000f;
+    if rcode != 0 {
+        return Err(AppError::ExternalServiceError(format!("DNS query failed with RCODE {}", rcode)));
+    }
+
+    let qdcount = u16::from_be_bytes([packet[4], packet[5]]);
+    let ancount = u16::from_be_bytes([packet[6], packet[7]]);
+    let mut offset = 12;
+
+    for _ in 0..qdcount {
+        offset = skip_name(packet, offset).ok_or_else(malformed)? + 4;
+        if offset > packet.len() {
+            return Err(malformed());
+        }
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..ancount {
+        offset = skip_name(packet, offset).ok_or_else(malformed)?;
+        let header = packet.get(offset..offset + 10).ok_or_else(malformed)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let rdlength = u16::from_be_bytes([header[8], header[9]]) as usize;
+        offset += 10;
+        let rdata = packet.get(offset..offset + rdlength).ok_or_else(malformed)?;
+        offset += rdlength;
+
+        if rtype != qtype {
+            continue;
+        }
+
+        match rtype {
+            TYPE_A | TYPE_AAAA => {
+                let ip = match rdata.len() {
+                    4 => IpAddr::from(<[u8; 4]>::try_from(rdata).map_err(|_| malformed())?),
+                    16 => IpAddr::from(<[u8; 16]>::try_from(rdata).map_err(|_| malformed())?),
+                    _ => return Err(malformed()),
+                };
+                answers.push(ip.to_string());
+            }
+            TYPE_TXT => {
+                // TXT 記錄由一個或多個 <長度><內容> 字串組成
+                let mut text = String::new();
+                let mut pos = 0;
+                while pos < rdata.len() {
+                    let len = rdata[pos] as usize;
+                    let chunk = rdata.get(pos + 1..pos + 1 + len).ok_or_else(malformed)?;
+                    text.push_str(&String::from_utf8_lossy(chunk));
+                    pos += 1 + len;
+                }
+                answers.push(text);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Some(answers))
+}
+
+/// 跳過封包中的網域名稱（支援壓縮指標）
+///
+/// # 返回
+///
+/// 名稱之後的位移，封包格式錯誤時返回 `None`
+fn skip_name(packet: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(offset)? as usize;
+        match len {
+            0 => return Some(offset + 1),
+            l if l & 0xc0 == 0xc0 => return packet.get(offset + 1).map(|_| offset + 2),
+            l => offset += 1 + l,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+    use crate::services::ip::IpSourceConfig;
+
+    /// 構建響應封包：標頭、`whoami.cloudflare` 問題與以壓縮指標指向問題名稱的答案
+    ///
+    /// # 參數
+    ///
+    /// - `id`: 響應 ID
+    /// - `flags`: 標頭旗標
+    /// - `answers`: 答案的記錄類型與 RDATA
+    fn response(id: u16, flags: u16, answers: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&flags.to_be_bytes());
+        packet.extend_from_slice(&1u16.to_be_bytes());
+        packet.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        packet.extend_from_slice(b"\x06whoami\x0acloudflare\x00");
+        packet.extend_from_slice(&TYPE_TXT.to_be_bytes());
+        packet.extend_from_slice(&3u16.to_be_bytes());
+
+        for (rtype, rdata) in answers {
+            packet.extend_from_slice(&[0xc0, 12]);
+            packet.extend_from_slice(&rtype.to_be_bytes());
+            packet.extend_from_slice(&1u16.to_be_bytes());
+            packet.extend_from_slice(&60u32.to_be_bytes());
+            packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            packet.extend_from_slice(rdata);
+        }
+        packet
+    }
+
+    #[test]
+    fn joins_txt_chunks() {
+        let rdata = b"\x07198.51.\x05100.7".to_vec();
+        let packet = response(7, 0x8180, &[(TYPE_TXT, rdata)]);
+        assert_eq!(parse_response(&packet, 7, TYPE_TXT).unwrap(), Some(vec!["198.51.100.7".to_string()]));
+    }
+
+    #[test]
+    fn follows_compressed_names_and_skips_other_types() {
+        let packet = response(7, 0x8180, &[
+            (5, b"\xc0\x0c".to_vec()),
+            (TYPE_A, vec![198, 51, 100, 7]),
+            (TYPE_AAAA, vec![0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
+        ]);
+        assert_eq!(parse_response(&packet, 7, TYPE_A).unwrap(), Some(vec!["198.51.100.7".to_string()]));
+        assert_eq!(parse_response(&packet, 7, TYPE_AAAA).unwrap(), Some(vec!["2001:db8::1".to_string()]));
+    }
+
+    #[test]
+    fn ignores_other_ids_and_queries() {
+        let packet = response(7, 0x8180, &[(TYPE_A, vec![198, 51, 100, 7])]);
+        assert_eq!(parse_response(&packet, 8, TYPE_A).unwrap(), None);
+
+        // 沒有 QR 旗標的封包不是響應
+        let packet = response(7, 0x0100, &[(TYPE_A, vec![198, 51, 100, 7])]);
+        assert_eq!(parse_response(&packet, 7, TYPE_A).unwrap(), None);
+    }
+
+    #[test]
+    fn reports_rcode() {
+        let packet = response(7, 0x8183, &[]);
+        let error = parse_response(&packet, 7, TYPE_A).unwrap_err();
+        assert!(matches!(&error, AppError::ExternalServiceError(message) if message.contains("RCODE 3")), "{:?}", error);
+    }
+
+    #[test]
+    fn rejects_truncated_packets() {
+        let packet = response(7, 0x8180, &[(TYPE_A, vec![198, 51, 100, 7])]);
+        for len in [0, 11, 20, 30, 40, packet.len() - 1] {
+            let error = parse_response(&packet[..len], 7, TYPE_A).unwrap_err();
+            assert!(matches!(error, AppError::InvalidIpResponse(_)), "length {}: {:?}", len, error);
+        }
+
+        // TXT 字串長度超出 RDATA
+        let packet = response(7, 0x8180, &[(TYPE_TXT, b"\x20198.51.100.7".to_vec())]);
+        assert!(parse_response(&packet, 7, TYPE_TXT).is_err());
+
+        // A 記錄的 RDATA 長度錯誤
+        let packet = response(7, 0x8180, &[(TYPE_A, vec![198, 51, 100])]);
+        assert!(parse_response(&packet, 7, TYPE_A).is_err());
+    }
+
+    #[tokio::test]
+    async fn queries_configured_resolver() {
+        let resolver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = resolver.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            let (len, peer) = resolver.recv_from(&mut buf).await.unwrap();
+            let query = buf[..len].to_vec();
+            let id = u16::from_be_bytes([query[0], query[1]]);
+
+            // 先回應 ID 不符的封包，應被忽略
+            resolver.send_to(&response(id.wrapping_add(1), 0x8180, &[(TYPE_TXT, b"\x08192.0.2.1".to_vec())]), peer).await.unwrap();
+            resolver.send_to(&response(id, 0x8180, &[(TYPE_TXT, b"\x0c198.51.100.7".to_vec())]), peer).await.unwrap();
+            query
+        });
+
+        let config: IpSourceConfig = serde_json::from_value(serde_json::json!({
+            "type": "dns",
+            "query": "whoami.cloudflare",
+            "resolver": address.to_string(),
+            "record_type": "txt",
+            "class": "ch"
+        }))
+        .unwrap();
+        let ip = config.build().unwrap().fetch(IpVersion::V4).await.unwrap();
+        assert_eq!(ip, "198.51.100.7".parse::<IpAddr>().unwrap());
+
+        // 查詢名稱、TXT 類型與 CHAOS 類別
+        let query = server.await.unwrap();
+        assert_eq!(&query[12..], b"\x06whoami\x0acloudflare\x00\x00\x10\x00\x03");
+    }
+}
@@ -0,0 +1,73 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::UdpSocket;
+use crate::error::AppError;
+use crate::services::http_client;
+use super::{settings, IpVersion};
+
+/// 用於檢查路由的全域地址（UDP connect 只查詢路由表，不會發送封包）
+const PROBE_V4: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53);
+const PROBE_V6: SocketAddr = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111)), 53);
+
+/// 取得指定版本的查詢所綁定的本機地址
+///
+/// 依序使用 `[ip]` 的 `local_ipv4` / `local_ipv6`、版本相符的 `[http] local_address`，
+/// 皆未設置時為該地址族的任意地址
+///
+/// # 參數
+///
+/// - `version`: IP 版本
+pub fn local_address(version: IpVersion) -> IpAddr {
+    let settings = settings();
+    let http_local = http_client::settings().local_address;
+    match version {
+        IpVersion::V4 => settings.local_ipv4.map(IpAddr::V4)
+            .or(http_local.filter(IpAddr::is_ipv4))
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        IpVersion::V6 => settings.local_ipv6.map(IpAddr::V6)
+            .or(http_local.filter(IpAddr::is_ipv6))
+            .unwrap_or(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    }
+}
+
+/// 建立綁定到指定版本本機地址的 UDP socket
+///
+/// # 參數
+///
+/// - `version`: IP 版本
+///
+/// # 錯誤
+///
+/// 主機不支援該地址族或設置的本機地址不存在時返回 `AppError::AddressFamilyUnavailable`
+pub async fn bind_udp(version: IpVersion) -> Result<UdpSocket, AppError> {
+    let local = local_address(version);
+    UdpSocket::bind(SocketAddr::new(local, 0))
+        .await
+        .map_err(|e| AppError::AddressFamilyUnavailable(format!("cannot bind {} socket to {}: {}", version, local, e)))
+}
+
+/// 確認主機可以透過指定版本連線到公網
+///
+/// # 參數
+///
+/// - `version`: IP 版本
+///
+/// # 返回
+///
+/// - `Result<IpAddr, AppError>`: 成功時返回應綁定的本機地址
+///
+/// # 錯誤
+///
+/// 主機沒有該版本的地址或路由時返回 `AppError::AddressFamilyUnavailable`
+pub async fn probe(version: IpVersion) -> Result<IpAddr, AppError> {
+    let socket = bind_udp(version).await?;
+    let target = match version {
+        IpVersion::V4 => PROBE_V4,
+        IpVersion::V6 => PROBE_V6,
+    };
+
+    socket.connect(target)
+        .await
+        .map_err(|e| AppError::AddressFamilyUnavailable(format!("host has no {} route: {}", version, e)))?;
+
+    Ok(local_address(version))
+}
@@ -0,0 +1,581 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Instant};
+use crate::error::AppError;
+use crate::services::http_client;
+use super::IpVersion;
+use super::source::IpSource;
+use log::debug;
+
+/// NAT-PMP / PCP 伺服器端口
+const NAT_PMP_PORT: u16 = 5351;
+/// SSDP 多播地址
+const SSDP_ADDR: ([u8; 4], u16) = ([239, 255, 255, 250], 1900);
+/// SSDP 搜尋等待時間
+const SSDP_TIMEOUT: Duration = Duration::from_secs(3);
+/// NAT-PMP / PCP 重傳等待時間（RFC 6886 建議從 250ms 開始倍增）
+const RETRANSMIT_TIMEOUTS: [Duration; 4] = [
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_millis(1000),
+    Duration::from_millis(2000),
+];
+/// 要搜尋的 UPnP 服務類型，依優先順序
+const UPNP_SERVICE_TYPES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// 路由器查詢協定
+///
+/// # 變體
+///
+/// - `Auto`: 依序嘗試 PCP、NAT-PMP、UPnP IGD
+/// - `Upnp`: UPnP IGD（SSDP + SOAP GetExternalIPAddress）
+/// - `Natpmp`: NAT-PMP（RFC 6886）
+/// - `Pcp`: PCP（RFC 6887）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewayProtocol {
+    #[default]
+    Auto,
+    Upnp,
+    Natpmp,
+    Pcp,
+}
+
+/// 向路由器查詢 WAN 端 IPv4 地址的來源
+///
+/// 不依賴任何第三方服務，適合位於家用路由器之後的部署
+///
+/// # 欄位
+///
+/// - `name`: 來源名稱
+/// - `protocol`: 查詢協定
+/// - `gateway`: 路由器地址（NAT-PMP / PCP 使用，未設置時使用預設路由的閘道）
+/// - `location`: UPnP 裝置描述 URL（未設置時透過 SSDP 搜尋）
+/// - `client`: HTTP 客戶端
+pub struct GatewaySource {
+    name: String,
+    protocol: GatewayProtocol,
+    gateway: Option<SocketAddr>,
+    location: Option<String>,
+    client: reqwest::Client,
+}
+
+impl GatewaySource {
+    /// 創建新的路由器來源
+    ///
+    /// # 參數
+    ///
+    /// - `protocol`: 查詢協定
+    /// - `gateway`: 路由器地址（可選）
+    /// - `location`: UPnP 裝置描述 URL（可選）
+    pub fn new(protocol: GatewayProtocol, gateway: Option<SocketAddr>, location: Option<String>) -> Self {
+        let name = match protocol {
+            GatewayProtocol::Auto => "gateway",
+            GatewayProtocol::Upnp => "upnp",
+            GatewayProtocol::Natpmp => "natpmp",
+            GatewayProtocol::Pcp => "pcp",
+        };
+
+        Self {
+            name: name.to_string(),
+            protocol,
+            gateway,
+            location,
+            client: http_client::direct_client(),
+        }
+    }
+
+    /// 取得 NAT-PMP / PCP 使用的路由器地址
+    fn gateway_addr(&self) -> Result<SocketAddr, AppError> {
+        if let Some(gateway) = self.gateway {
+            return Ok(gateway);
+        }
+
+        default_gateway()
+            .map(|ip| SocketAddr::new(IpAddr::V4(ip), NAT_PMP_PORT))
+            .ok_or_else(|| AppError::AddressFamilyUnavailable("Unable to determine default gateway, set `gateway` explicitly".to_string()))
+    }
+
+    /// 透過 NAT-PMP 查詢外部地址
+    async fn query_nat_pmp(&self) -> Result<IpAddr, AppError> {
+        let gateway = self.gateway_addr()?;
+        let socket = connect_udp(gateway).await?;
+
+        // 版本 0，操作碼 0（外部地址查詢）
+        let response = exchange(&socket, &[0, 0], |packet| packet.len() >= 12 && packet[0] == 0 && packet[1] == 128).await?;
+
+        let result = u16::from_be_bytes([response[2], response[3]]);
+        if result != 0 {
+            return Err(AppError::ExternalServiceError(format!("NAT-PMP gateway returned result code {}", result)));
+        }
+
+        Ok(IpAddr::V4(Ipv4Addr::new(response[8], response[9], response[10], response[11])))
+    }
+
+    /// 透過 PCP MAP 請求查詢外部地址
+    ///
+    /// 建立一個短期的 UDP 映射以取得分配的外部地址，隨後立即刪除該映射
+    async fn query_pcp(&self) -> Result<IpAddr, AppError> {
+        let gateway = self.gateway_addr()?;
+        let socket = connect_udp(gateway).await?;
+        let local = socket.local_addr()
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to get local address: {}", e)))?;
+
+        let nonce: [u8; 12] = rand::random();
+        let request = build_pcp_map(&nonce, local, 30);
+        let matches = |packet: &[u8]| packet.len() >= 60 && packet[0] == 2 && packet[1] == 0x81 && packet[24..36] == nonce;
+        let response = exchange(&socket, &request, matches).await?;
+
+        let result = response[3];
+        if result != 0 {
+            return Err(AppError::ExternalServiceError(format!("PCP gateway returned result code {}", result)));
+        }
+
+        let octets: [u8; 16] = response[44..60].try_into()
+            .map_err(|_| AppError::InvalidIpResponse("malformed PCP response".to_string()))?;
+        let external = Ipv6Addr::from(octets);
+
+        // 刪除剛建立的映射（生命週期 0），失敗不影響結果
+        let _ = socket.send(&build_pcp_map(&nonce, local, 0)).await;
+
+        Ok(external.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(external)))
+    }
+
+    /// 透過 UPnP IGD 查詢外部地址
+    async fn query_upnp(&self) -> Result<IpAddr, AppError> {
+        let location = match &self.location {
+            Some(location) => location.clone(),
+            None => discover_igd().await?,
+        };
+        debug!("Using UPnP device description at {}", location);
+
+        let description = self.client.get(&location)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to fetch UPnP description: {}", e)))?
+            .text()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        let (service_type, control_url) = find_wan_service(&description)
+            .ok_or_else(|| AppError::ExternalServiceError("No WANIPConnection service found on UPnP gateway".to_string()))?;
+        let control_url = reqwest::Url::parse(&location)
+            .and_then(|base| base.join(&control_url))
+            .map_err(|e| AppError::ExternalServiceError(format!("Invalid UPnP control URL: {}", e)))?;
+
+        let body = format!(
+            "<?xml version=\"1.0\"?>\
+            <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+            <s:Body><u:GetExternalIPAddress xmlns:u=\"{}\"></u:GetExternalIPAddress></s:Body>\
+            </s:Envelope>",
+            service_type
+        );
+
+        let response = self.client.post(control_url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header("SOAPAction", format!("\"{}#GetExternalIPAddress\"", service_type))
+            .body(body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::ExternalServiceError(format!("UPnP GetExternalIPAddress failed: {}", e)))?
+            .text()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        let address = xml_text(&response, "NewExternalIPAddress")
+            .ok_or_else(|| AppError::InvalidIpResponse("UPnP response has no NewExternalIPAddress".to_string()))?;
+
+        super::parse_ip(address, IpVersion::V4)
+    }
+}
+
+#[async_trait]
+impl IpSource for GatewaySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        if version != IpVersion::V4 {
+            return Err(AppError::ConfigError(format!("{} only supports IPv4", self.name)));
+        }
+
+        let ip = match self.protocol {
+            GatewayProtocol::Natpmp => self.query_nat_pmp().await?,
+            GatewayProtocol::Pcp => self.query_pcp().await?,
+            GatewayProtocol::Upnp => self.query_upnp().await?,
+            GatewayProtocol::Auto => {
+                let mut failures = Vec::new();
+                let mut found = None;
+                for protocol in [GatewayProtocol::Pcp, GatewayProtocol::Natpmp, GatewayProtocol::Upnp] {
+                    let result = match protocol {
+                        GatewayProtocol::Pcp => self.query_pcp().await,
+                        GatewayProtocol::Natpmp => self.query_nat_pmp().await,
+                        _ => self.query_upnp().await,
+                    };
+                    match result {
+                        Ok(ip) => {
+                            found = Some(ip);
+                            break;
+                        }
+                        Err(e) => failures.push(format!("{:?}: {}", protocol, e)),
+                    }
+                }
+                found.ok_or_else(|| AppError::ExternalServiceError(format!(
+                    "No gateway protocol succeeded: {}", failures.join("; ")
+                )))?
+            }
+        };
+
+        super::check_version(ip, version)
+    }
+}
+
+/// 解析路由器地址字串，未指定端口時使用 5351
+///
+/// # 參數
+///
+/// - `gateway`: 路由器地址（`192.168.1.1` 或 `192.168.1.1:5351`）
+pub fn parse_gateway(gateway: &str) -> Result<SocketAddr, AppError> {
+    let gateway = gateway.trim();
+    gateway
+        .parse::<SocketAddr>()
+        .or_else(|_| gateway.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, NAT_PMP_PORT)))
+        .map_err(|_| AppError::ConfigError(format!("Invalid gateway address: {}", gateway)))
+}
+
+/// 從 `/proc/net/route` 讀取預設路由的閘道
+fn default_gateway() -> Option<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let destination = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+        let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        let flags = u16::from_str_radix(fields.get(3)?, 16).ok()?;
+        // RTF_GATEWAY = 0x2，欄位為主機位元組序
+        (destination == 0 && flags & 0x2 != 0).then(|| Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+/// 建立連線到指定地址的 UDP socket
+async fn connect_udp(addr: SocketAddr) -> Result<UdpSocket, AppError> {
+    let bind_addr: SocketAddr = match addr {
+        SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    };
+    let socket = UdpSocket::bind(bind_addr)
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Failed to bind UDP socket: {}", e)))?;
+    socket.connect(addr)
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Failed to connect to {}: {}", addr, e)))?;
+    Ok(socket)
+}
+
+/// 發送請求並等待符合條件的響應，逾時時重傳
+async fn exchange(socket: &UdpSocket, request: &[u8], matches: impl Fn(&[u8]) -> bool) -> Result<Vec<u8>, AppError> {
+    let mut buf = [0u8; 1100];
+
+    for wait in RETRANSMIT_TIMEOUTS {
+        socket.send(request)
+            .await
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to send request: {}", e)))?;
+
+        let received = timeout(wait, async {
+            loop {
+                let len = socket.recv(&mut buf).await?;
+                if matches(&buf[..len]) {
+                    return Ok::<_, std::io::Error>(buf[..len].to_vec());
+                }
+            }
+        })
+        .await;
+
+        match received {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(e)) => return Err(AppError::ExternalServiceError(format!("Failed to receive response: {}", e))),
+            Err(_) => continue,
+        }
+    }
+
+    Err(AppError::ExternalServiceError("Gateway did not respond".to_string()))
+}
+
+/// 構建 PCP MAP 請求（UDP，建議外部地址為 IPv4 任意地址）
+fn build_pcp_map(nonce: &[u8; 12], local: SocketAddr, lifetime: u32) -> Vec<u8> {
+    let client_ip = match local.ip() {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    };
+
+    let mut request = Vec::with_capacity(60);
+    request.extend_from_slice(&[2, 1, 0, 0]); // 版本 2，MAP 請求
+    request.extend_from_slice(&lifetime.to_be_bytes());
+    request.extend_from_slice(&client_ip.octets());
+    request.extend_from_slice(nonce);
+    request.extend_from_slice(&[17, 0, 0, 0]); // UDP
+    request.extend_from_slice(&local.port().to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+    request
+}
+
+/// 透過 SSDP 搜尋網際網路閘道裝置
+///
+/// # 返回
+///
+/// - `Result<String, AppError>`: 成功時返回裝置描述 URL（LOCATION 標頭）
+async fn discover_igd() -> Result<String, AppError> {
+    let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Failed to bind SSDP socket: {}", e)))?;
+
+    let request = "M-SEARCH * HTTP/1.1\r\n\
+        HOST: 239.255.255.250:1900\r\n\
+        MAN: \"ssdp:discover\"\r\n\
+        MX: 2\r\n\
+        ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n";
+    socket.send_to(request.as_bytes(), SocketAddr::from(SSDP_ADDR))
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Failed to send SSDP search: {}", e)))?;
+
+    let deadline = Instant::now() + SSDP_TIMEOUT;
+    let mut buf = [0u8; 2048];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let (len, _) = timeout(remaining, socket.recv_from(&mut buf))
+            .await
+            .map_err(|_| AppError::ExternalServiceError("No UPnP gateway responded to SSDP search".to_string()))?
+            .map_err(|e| AppError::ExternalServiceError(format!("Failed to receive SSDP response: {}", e)))?;
+
+        let response = String::from_utf8_lossy(&buf[..len]);
+        let location = response.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("location").then(|| value.trim().to_string())
+        });
+
+        if let Some(location) = location {
+            return Ok(location);
+        }
+    }
+}
+
+/// 在裝置描述中尋找 WAN 連線服務
+///
+/// # 返回
+///
+/// 服務類型與控制 URL
+fn find_wan_service(description: &str) -> Option<(String, String)> {
+    let services: Vec<&str> = description.split("<service>").skip(1).collect();
+
+    UPNP_SERVICE_TYPES.iter().find_map(|wanted| {
+        services.iter().find_map(|service| {
+            if xml_text(service, "serviceType")? != *wanted {
+                return None;
+            }
+            Some((wanted.to_string(), xml_text(service, "controlURL")?.to_string()))
+        })
+    })
+}
+
+/// 取得第一個指定標籤的文字內容（忽略命名空間前綴）
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = xml.find(&format!("{}>", tag))? + tag.len() + 1;
+    let close = open + xml[open..].find("</")?;
+    Some(xml[open..close].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use crate::services::ip::IpSourceConfig;
+
+    /// 根據配置建立路由器來源
+    fn source(config: serde_json::Value) -> Box<dyn IpSource> {
+        serde_json::from_value::<IpSourceConfig>(config).unwrap().build().unwrap()
+    }
+
+    /// 在 127.0.0.1 上啟動假的 NAT-PMP / PCP 路由器，對每個請求呼叫 `respond`，返回收到的請求
+    async fn fake_gateway(
+        requests: usize,
+        respond: impl Fn(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    ) -> (SocketAddr, tokio::task::JoinHandle<Vec<(Vec<u8>, SocketAddr)>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1100];
+            while received.len() < requests {
+                let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+                for response in respond(&buf[..len]) {
+                    socket.send_to(&response, peer).await.unwrap();
+                }
+                received.push((buf[..len].to_vec(), peer));
+            }
+            received
+        });
+        (address, handle)
+    }
+
+    /// PCP MAP 響應（RFC 6887 第 7.2 與 11.1 節）
+    fn pcp_response(request: &[u8], result: u8, external: Ipv4Addr) -> Vec<u8> {
+        let mut response = vec![2, 0x81, 0, result];
+        response.extend_from_slice(&request[4..8]); // 生命週期
+        response.extend_from_slice(&1234u32.to_be_bytes()); // epoch
+        response.extend_from_slice(&[0; 12]);
+        response.extend_from_slice(&request[24..44]); // nonce、協定、保留、內部端口、建議外部端口
+        response.extend_from_slice(&external.to_ipv6_mapped().octets());
+        response
+    }
+
+    #[tokio::test]
+    async fn pcp_maps_and_releases_port() {
+        let (address, gateway) = fake_gateway(2, |request| {
+            // 先回應 nonce 不符的封包，應被忽略
+            let mut other = pcp_response(request, 0, Ipv4Addr::new(192, 0, 2, 1));
+            other[24] ^= 0xff;
+            vec![other, pcp_response(request, 0, Ipv4Addr::new(198, 51, 100, 7))]
+        })
+        .await;
+
+        let source = source(serde_json::json!({ "type": "gateway", "protocol": "pcp", "gateway": address.to_string() }));
+        assert_eq!(source.fetch(IpVersion::V4).await.unwrap(), IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)));
+
+        let requests = gateway.await.unwrap();
+        let (map, peer) = &requests[0];
+        assert_eq!(map.len(), 60);
+        assert_eq!(map[..4], [2, 1, 0, 0]);
+        assert_eq!(u32::from_be_bytes(map[4..8].try_into().unwrap()), 30);
+        assert_eq!(map[8..24], Ipv4Addr::LOCALHOST.to_ipv6_mapped().octets());
+        assert_eq!(map[36], 17);
+        assert_eq!(u16::from_be_bytes([map[40], map[41]]), peer.port());
+        assert_eq!(map[44..60], Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+
+        // 相同 nonce、生命週期 0 的請求刪除映射
+        let (delete, _) = &requests[1];
+        assert_eq!(delete[24..36], map[24..36]);
+        assert_eq!(u32::from_be_bytes(delete[4..8].try_into().unwrap()), 0);
+    }
+
+    #[tokio::test]
+    async fn pcp_reports_result_code() {
+        let (address, _gateway) = fake_gateway(1, |request| vec![pcp_response(request, 2, Ipv4Addr::UNSPECIFIED)]).await;
+
+        let source = source(serde_json::json!({ "type": "gateway", "protocol": "pcp", "gateway": address.to_string() }));
+        let error = source.fetch(IpVersion::V4).await.unwrap_err();
+        assert!(error.to_string().contains("result code 2"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn nat_pmp_reads_external_address() {
+        let (address, gateway) = fake_gateway(1, |_| {
+            // 版本 0，操作碼 128，結果 0，epoch，外部地址
+            vec![vec![0, 128, 0, 0, 0, 0, 4, 210, 198, 51, 100, 7]]
+        })
+        .await;
+
+        let source = source(serde_json::json!({ "type": "gateway", "protocol": "natpmp", "gateway": address.to_string() }));
+        assert_eq!(source.fetch(IpVersion::V4).await.unwrap(), IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)));
+        assert_eq!(gateway.await.unwrap()[0].0, [0, 0]);
+    }
+
+    #[tokio::test]
+    async fn nat_pmp_reports_result_code() {
+        let (address, _gateway) = fake_gateway(1, |_| vec![vec![0, 128, 0, 3, 0, 0, 4, 210, 0, 0, 0, 0]]).await;
+
+        let source = source(serde_json::json!({ "type": "gateway", "protocol": "natpmp", "gateway": address.to_string() }));
+        let error = source.fetch(IpVersion::V4).await.unwrap_err();
+        assert!(error.to_string().contains("result code 3"), "{}", error);
+    }
+
+    /// 包含 WANPPPConnection 與 WANIPConnection:1 的裝置描述
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:WANPPPConnection:1</serviceType>
+        <controlURL>/ppp</controlURL>
+      </service>
+      <service>
+        <serviceType> urn:schemas-upnp-org:service:WANIPConnection:1 </serviceType>
+        <controlURL>/ctl/IPConn</controlURL>
+      </service>
+    </serviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn finds_preferred_wan_service() {
+        assert_eq!(
+            find_wan_service(DESCRIPTION),
+            Some(("urn:schemas-upnp-org:service:WANIPConnection:1".to_string(), "/ctl/IPConn".to_string()))
+        );
+
+        let ppp_only = DESCRIPTION.replace("WANIPConnection", "WANIPv6FirewallControl");
+        assert_eq!(find_wan_service(&ppp_only).unwrap().1, "/ppp");
+        assert_eq!(find_wan_service("<root><device></device></root>"), None);
+    }
+
+    #[test]
+    fn reads_namespaced_xml_text() {
+        let response = "<s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+            <NewExternalIPAddress> 198.51.100.7 </NewExternalIPAddress>\
+            </u:GetExternalIPAddressResponse></s:Body></s:Envelope>";
+        assert_eq!(xml_text(response, "NewExternalIPAddress"), Some("198.51.100.7"));
+        assert_eq!(xml_text("<m:controlURL>/ctl</m:controlURL>", "controlURL"), Some("/ctl"));
+        assert_eq!(xml_text("<controlURL>/ctl", "controlURL"), None);
+        assert_eq!(xml_text(response, "NewConnectionStatus"), None);
+    }
+
+    #[tokio::test]
+    async fn upnp_queries_igd_at_location() {
+        async fn control(request: HttpRequest, body: String) -> HttpResponse {
+            let action = request.headers().get("SOAPAction").and_then(|value| value.to_str().ok()).unwrap_or_default();
+            if action != "\"urn:schemas-upnp-org:service:WANIPConnection:1#GetExternalIPAddress\""
+                || !body.contains("<u:GetExternalIPAddress xmlns:u=\"urn:schemas-upnp-org:service:WANIPConnection:1\">")
+            {
+                return HttpResponse::InternalServerError().finish();
+            }
+            HttpResponse::Ok().content_type("text/xml").body(
+                "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\"><s:Body>\
+                <u:GetExternalIPAddressResponse xmlns:u=\"urn:schemas-upnp-org:service:WANIPConnection:1\">\
+                <NewExternalIPAddress>198.51.100.7</NewExternalIPAddress>\
+                </u:GetExternalIPAddressResponse></s:Body></s:Envelope>",
+            )
+        }
+
+        let server = HttpServer::new(|| {
+            App::new()
+                .route("/igd/desc.xml", web::get().to(|| async { HttpResponse::Ok().content_type("text/xml").body(DESCRIPTION) }))
+                .route("/ctl/IPConn", web::post().to(control))
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        let source = source(serde_json::json!({
+            "type": "gateway",
+            "protocol": "upnp",
+            "location": format!("http://{}/igd/desc.xml", address)
+        }));
+        assert_eq!(source.fetch(IpVersion::V4).await.unwrap(), IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)));
+        handle.stop(false).await;
+    }
+}
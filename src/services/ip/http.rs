@@ -0,0 +1,226 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::AppError;
+use crate::services::http_client;
+use super::{family, IpVersion, parse_ip};
+use super::source::IpSource;
+
+/// HTTP 回顯服務的響應格式
+///
+/// # 變體
+///
+/// - `PlainText`: 響應內容即為 IP 地址
+/// - `KeyValue`: 響應為 `key=value` 行，IP 地址位於指定鍵
+#[derive(Debug, Clone)]
+pub enum ResponseFormat {
+    PlainText,
+    KeyValue(String),
+}
+
+/// 基於 HTTP 回顯服務的 IP 來源
+///
+/// # 欄位
+///
+/// - `name`: 來源名稱
+/// - `ipv4_url`: 查詢 IPv4 的 URL（可選）
+/// - `ipv6_url`: 查詢 IPv6 的 URL（可選）
+/// - `format`: 響應格式
+pub struct HttpSource {
+    name: String,
+    ipv4_url: Option<String>,
+    ipv6_url: Option<String>,
+    format: ResponseFormat,
+}
+
+impl HttpSource {
+    /// 創建新的 HTTP 來源
+    ///
+    /// # 參數
+    ///
+    /// - `name`: 來源名稱
+    /// - `ipv4_url`: 查詢 IPv4 的 URL（可選）
+    /// - `ipv6_url`: 查詢 IPv6 的 URL（可選）
+    /// - `format`: 響應格式
+    pub fn new(name: &str, ipv4_url: Option<String>, ipv6_url: Option<String>, format: ResponseFormat) -> Self {
+        Self {
+            name: name.to_string(),
+            ipv4_url,
+            ipv6_url,
+            format,
+        }
+    }
+
+    /// ipify（api4.ipify.org / api6.ipify.org）
+    pub fn ipify() -> Self {
+        Self::new(
+            "ipify",
+            Some("https://api4.ipify.org".to_string()),
+            Some("https://api6.ipify.org".to_string()),
+            ResponseFormat::PlainText,
+        )
+    }
+
+    /// icanhazip（ipv4.icanhazip.com / ipv6.icanhazip.com）
+    pub fn icanhazip() -> Self {
+        Self::new(
+            "icanhazip",
+            Some("https://ipv4.icanhazip.com".to_string()),
+            Some("https://ipv6.icanhazip.com".to_string()),
+            ResponseFormat::PlainText,
+        )
+    }
+
+    /// Cloudflare `cdn-cgi/trace`，從 `ip=` 行解析地址
+    pub fn cloudflare() -> Self {
+        Self::new(
+            "cloudflare",
+            Some("https://1.1.1.1/cdn-cgi/trace".to_string()),
+            Some("https://[2606:4700:4700::1111]/cdn-cgi/trace".to_string()),
+            ResponseFormat::KeyValue("ip".to_string()),
+        )
+    }
+
+    /// 解析響應內容
+    ///
+    /// # 參數
+    ///
+    /// - `body`: 響應內容
+    /// - `version`: 預期的 IP 版本
+    ///
+    /// # 返回
+    ///
+    /// - `Result<IpAddr, AppError>`: 成功時返回 IP 地址，內容不是預期版本的 IP 地址時返回錯誤
+    fn parse_body(&self, body: &str, version: IpVersion) -> Result<IpAddr, AppError> {
+        let value = match &self.format {
+            ResponseFormat::PlainText => body,
+            ResponseFormat::KeyValue(key) => body
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(k, _)| k.trim() == key)
+                .map(|(_, v)| v)
+                .ok_or_else(|| AppError::InvalidIpResponse(format!(
+                    "{}: key '{}' not found in response", self.name, key
+                )))?,
+        };
+
+        parse_ip(value, version)
+    }
+}
+
+#[async_trait]
+impl IpSource for HttpSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        let url = match version {
+            IpVersion::V4 => self.ipv4_url.as_deref(),
+            IpVersion::V6 => self.ipv6_url.as_deref(),
+        }
+        .ok_or_else(|| AppError::ConfigError(format!("{} does not support {}", self.name, version)))?;
+
+        // 綁定到請求版本的本機地址，避免 IPv6 查詢經由 IPv4 完成（反之亦然）；
+        // 使用代理時觀察到的是代理的出口地址，無法限定地址族
+        let client = if http_client::settings().proxy.is_some() {
+            http_client::client()
+        } else {
+            http_client::bound_client(family::probe(version).await?)
+        };
+
+        let response = client.get(url)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::ExternalServiceError(format!(
+                "{} returned HTTP {}", url, response.status()
+            )));
+        }
+
+        let body = response.text()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        self.parse_body(&body, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 啟動以固定狀態與內容回應的 HTTP 回顯服務，返回其 URL
+    async fn echo_server(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0u8; 1024];
+                let _ = stream.read(&mut buffer).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    fn trace() -> HttpSource {
+        HttpSource::cloudflare()
+    }
+
+    #[test]
+    fn parses_ip_line_among_other_keys() {
+        let body = "fl=123f45\nh=1.1.1.1\nvip=192.0.2.1\nip=198.51.100.7\nts=1700000000.123\nvisit_scheme=https\nloc=TW\n";
+        assert_eq!(trace().parse_body(body, IpVersion::V4).unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn tolerates_whitespace_around_key_and_value() {
+        let body = "h=1.1.1.1\r\n  ip =  2001:db8::7 \r\nts=1700000000.123\r\n";
+        assert_eq!(trace().parse_body(body, IpVersion::V6).unwrap(), "2001:db8::7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn missing_ip_line_is_invalid_response() {
+        let error = trace().parse_body("fl=123f45\nh=1.1.1.1\nvip=192.0.2.1\n", IpVersion::V4).unwrap_err();
+        assert!(matches!(&error, AppError::InvalidIpResponse(message) if message.contains("'ip' not found")), "{:?}", error);
+    }
+
+    #[test]
+    fn invalid_ip_value_is_invalid_response() {
+        let error = trace().parse_body("ip=unknown\n", IpVersion::V4).unwrap_err();
+        assert!(matches!(error, AppError::InvalidIpResponse(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn plain_text_uses_whole_body() {
+        let source = HttpSource::ipify();
+        assert_eq!(source.parse_body("198.51.100.7\n", IpVersion::V4).unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_non_success_status() {
+        let url = echo_server("503 Service Unavailable", "198.51.100.7").await;
+        let source = HttpSource::new("echo", Some(url), None, ResponseFormat::PlainText);
+
+        let error = source.fetch(IpVersion::V4).await.unwrap_err();
+        assert!(matches!(&error, AppError::ExternalServiceError(message) if message.contains("HTTP 503")), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn rejects_html_body_with_success_status() {
+        let url = echo_server("200 OK", "<html><body>Sign in to continue</body></html>").await;
+        let source = HttpSource::new("echo", Some(url), None, ResponseFormat::PlainText);
+
+        assert!(matches!(source.fetch(IpVersion::V4).await, Err(AppError::InvalidIpResponse(_))));
+    }
+}
@@ -0,0 +1,132 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::AppError;
+use super::IpVersion;
+use super::source::IpSource;
+
+/// 讀取本機網路介面地址的 IP 來源
+///
+/// 適用於公網地址直接綁定在介面上的主機（VPS、IPv6 主機），不需要外部回顯服務。
+/// 會排除 loopback、link-local、ULA 以及臨時（隱私擴充）、已棄用或尚未通過 DAD 的 IPv6 地址
+///
+/// # 欄位
+///
+/// - `interface`: 介面名稱（例如 `eth0`）
+/// - `name`: 來源名稱
+pub struct InterfaceSource {
+    interface: String,
+    name: String,
+}
+
+impl InterfaceSource {
+    /// 創建新的介面來源
+    ///
+    /// # 參數
+    ///
+    /// - `interface`: 介面名稱（例如 `eth0`）
+    pub fn new(interface: &str) -> Self {
+        Self {
+            interface: interface.to_string(),
+            name: format!("interface:{}", interface),
+        }
+    }
+}
+
+#[async_trait]
+impl IpSource for InterfaceSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        use super::netlink;
+
+        let interface = self.interface.clone();
+        let addresses = tokio::task::spawn_blocking(move || {
+            let index = netlink::interface_index(&interface)?;
+            let addresses = netlink::dump_addresses(version)?;
+            Ok::<_, std::io::Error>(addresses.into_iter().filter(|a| a.index == index).collect::<Vec<_>>())
+        })
+        .await
+        .map_err(|e| AppError::InternalError(e.to_string()))?
+        .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        addresses
+            .iter()
+            .find(|a| is_usable(a))
+            .map(|a| a.address)
+            .ok_or_else(|| AppError::ExternalServiceError(format!(
+                "no usable {} address on interface {}", version, self.interface
+            )))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn fetch(&self, _version: IpVersion) -> Result<IpAddr, AppError> {
+        Err(AppError::ConfigError("Interface source is only supported on Linux".to_string()))
+    }
+}
+
+/// 判斷介面地址是否可以作為公網地址發布
+#[cfg(target_os = "linux")]
+pub(crate) fn is_usable(address: &super::netlink::InterfaceAddress) -> bool {
+    // 這些旗標只對 IPv6 有意義（IPv4 的 0x01 為 IFA_F_SECONDARY）
+    const EXCLUDED_V6_FLAGS: u32 = libc::IFA_F_TEMPORARY
+        | libc::IFA_F_DEPRECATED
+        | libc::IFA_F_TENTATIVE
+        | libc::IFA_F_DADFAILED;
+
+    match address.address {
+        IpAddr::V4(ip) => !(ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()),
+        IpAddr::V6(ip) => !(address.flags & EXCLUDED_V6_FLAGS != 0
+            || ip.is_loopback()
+            || ip.is_unspecified()
+            || ip.is_unicast_link_local()
+            || ip.is_unique_local()),
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use super::super::netlink::InterfaceAddress;
+
+    fn address(address: &str, flags: u32) -> InterfaceAddress {
+        InterfaceAddress {
+            index: 2,
+            address: address.parse().unwrap(),
+            prefix_len: 64,
+            scope: libc::RT_SCOPE_UNIVERSE,
+            flags,
+        }
+    }
+
+    #[test]
+    fn accepts_stable_global_addresses() {
+        assert!(is_usable(&address("2001:db8::7", 0)));
+        assert!(is_usable(&address("2001:db8::7", libc::IFA_F_PERMANENT | libc::IFA_F_MANAGETEMPADDR)));
+        assert!(is_usable(&address("198.51.100.7", 0)));
+        // IPv4 的 0x01 為 IFA_F_SECONDARY，不排除
+        assert!(is_usable(&address("198.51.100.7", libc::IFA_F_SECONDARY)));
+    }
+
+    #[test]
+    fn rejects_temporary_and_deprecated_ipv6_addresses() {
+        assert!(!is_usable(&address("2001:db8::7", libc::IFA_F_TEMPORARY)));
+        assert!(!is_usable(&address("2001:db8::7", libc::IFA_F_DEPRECATED)));
+        assert!(!is_usable(&address("2001:db8::7", libc::IFA_F_TENTATIVE)));
+        assert!(!is_usable(&address("2001:db8::7", libc::IFA_F_DADFAILED)));
+    }
+
+    #[test]
+    fn rejects_local_scopes() {
+        assert!(!is_usable(&address("fd00::7", 0)));
+        assert!(!is_usable(&address("fc00::7", 0)));
+        assert!(!is_usable(&address("fe80::7", 0)));
+        assert!(!is_usable(&address("::1", 0)));
+        assert!(!is_usable(&address("::", 0)));
+        assert!(!is_usable(&address("169.254.1.1", 0)));
+        assert!(!is_usable(&address("127.0.0.1", 0)));
+        assert!(!is_usable(&address("0.0.0.0", 0)));
+    }
+}
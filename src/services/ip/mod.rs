@@ -0,0 +1,447 @@
+pub mod source;
+pub mod http;
+pub mod quorum;
+pub mod interface;
+pub mod dns;
+pub mod stun;
+pub mod gateway;
+pub mod classify;
+pub mod family;
+pub mod cache;
+#[cfg(target_os = "linux")]
+pub mod netlink;
+pub mod monitor;
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::OnceLock;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::config::settings::IpSettings;
+use crate::error::AppError;
+use log::warn;
+
+pub use source::{IpSource, IpSourceChain};
+pub use http::HttpSource;
+pub use quorum::QuorumSource;
+pub use interface::InterfaceSource;
+pub use dns::DnsSource;
+pub use stun::StunSource;
+pub use gateway::GatewaySource;
+pub use classify::{classify, AddressClass, NonPublicPolicy};
+pub use monitor::AddressMonitor;
+pub use cache::IpObserver;
+
+/// 錯誤訊息中保留的響應內容最大長度
+const MAX_RESPONSE_EXCERPT: usize = 64;
+
+/// 未配置時使用的預設來源順序
+const DEFAULT_SOURCE_NAMES: [&str; 3] = ["ipify", "icanhazip", "cloudflare"];
+
+/// 全域 IP 偵測設置（由 `config/default.toml` 的 `[ip]` 區段設置）
+static SETTINGS: OnceLock<IpSettings> = OnceLock::new();
+
+/// 全域 IP 觀察者
+static OBSERVER: OnceLock<IpObserver> = OnceLock::new();
+
+/// IP 版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    /// 從配置中的 IP 類型字串解析
+    ///
+    /// # 參數
+    ///
+    /// - `ip_type`: IP 類型（"ipv4" 或 "ipv6"）
+    ///
+    /// # 返回
+    ///
+    /// - `Result<IpVersion, AppError>`: 成功時返回 IP 版本，失敗時返回錯誤
+    pub fn from_ip_type(ip_type: &str) -> Result<Self, AppError> {
+        match ip_type {
+            "ipv4" => Ok(IpVersion::V4),
+            "ipv6" => Ok(IpVersion::V6),
+            _ => Err(AppError::ConfigError(format!("Invalid IP type: {}", ip_type)))
+        }
+    }
+}
+
+impl fmt::Display for IpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpVersion::V4 => write!(f, "IPv4"),
+            IpVersion::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// IP 來源配置
+///
+/// 可以寫成完整形式（`{ "type": "custom", "url": "..." }`），
+/// 或簡寫為來源名稱（`"ipify"`、`"icanhazip"`、`"cloudflare"`、`"opendns"`、`"cloudflare_dns"`、`"stun"`、
+/// `"gateway"`、`"upnp"`、`"natpmp"`、`"pcp"`）、`"interface:eth0"`、`"stun:host:port"` 或 URL 字串
+///
+/// # 變體
+///
+/// - `Ipify`: api4/api6.ipify.org
+/// - `Icanhazip`: ipv4/ipv6.icanhazip.com
+/// - `Cloudflare`: Cloudflare `cdn-cgi/trace`
+/// - `Custom`: 自訂 URL，`ipv6_url` 未設置時 IPv6 也使用 `url`，設置 `key` 時以 `key=value` 格式解析
+/// - `Interface`: 讀取本機網路介面上的地址（僅限 Linux）
+/// - `Opendns`: 透過 DNS 查詢 `myip.opendns.com`
+/// - `CloudflareDns`: 透過 DNS 查詢 CHAOS 類別的 `whoami.cloudflare`
+/// - `Dns`: 自訂 DNS 查詢，`resolver` 依其地址族用於 IPv4 或 IPv6，`ipv6_resolver` 可另外指定 IPv6 解析器
+/// - `Stun`: 向 STUN 伺服器發送 Binding Request，依序嘗試 `servers`
+/// - `Gateway`: 向路由器查詢 WAN 端 IPv4 地址（UPnP IGD / NAT-PMP / PCP），`gateway` 未設置時使用預設路由的閘道，
+///   `location` 可指定 UPnP 裝置描述 URL 以略過 SSDP 搜尋
+/// - `Named`: 簡寫形式
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpSourceConfig {
+    Ipify,
+    Icanhazip,
+    Cloudflare,
+    Custom {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ipv6_url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<String>,
+    },
+    Interface {
+        interface: String,
+    },
+    Opendns,
+    CloudflareDns,
+    Dns {
+        query: String,
+        resolver: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ipv6_resolver: Option<String>,
+        #[serde(default)]
+        record_type: dns::DnsRecordType,
+        #[serde(default)]
+        class: dns::DnsClass,
+    },
+    Stun {
+        #[serde(default = "stun::default_servers")]
+        servers: Vec<String>,
+    },
+    Gateway {
+        #[serde(default)]
+        protocol: gateway::GatewayProtocol,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gateway: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<String>,
+    },
+    #[serde(untagged)]
+    Named(String),
+}
+
+impl IpSourceConfig {
+    /// 根據配置建立 IP 來源
+    ///
+    /// # 返回
+    ///
+    /// - `Result<Box<dyn IpSource>, AppError>`: 成功時返回 IP 來源，失敗時返回錯誤
+    pub fn build(&self) -> Result<Box<dyn IpSource>, AppError> {
+        match self {
+            IpSourceConfig::Ipify => Ok(Box::new(HttpSource::ipify())),
+            IpSourceConfig::Icanhazip => Ok(Box::new(HttpSource::icanhazip())),
+            IpSourceConfig::Cloudflare => Ok(Box::new(HttpSource::cloudflare())),
+            IpSourceConfig::Custom { url, ipv6_url, key } => {
+                let format = match key {
+                    Some(key) => http::ResponseFormat::KeyValue(key.clone()),
+                    None => http::ResponseFormat::PlainText,
+                };
+                let ipv6_url = ipv6_url.clone().unwrap_or_else(|| url.clone());
+                Ok(Box::new(HttpSource::new(url, Some(url.clone()), Some(ipv6_url), format)))
+            }
+            IpSourceConfig::Interface { interface } => {
+                if interface.trim().is_empty() {
+                    return Err(AppError::ConfigError("Interface name cannot be empty".to_string()));
+                }
+                Ok(Box::new(InterfaceSource::new(interface)))
+            }
+            IpSourceConfig::Opendns => Ok(Box::new(DnsSource::opendns())),
+            IpSourceConfig::CloudflareDns => Ok(Box::new(DnsSource::cloudflare())),
+            IpSourceConfig::Dns { query, resolver, ipv6_resolver, record_type, class } => {
+                let resolver = dns::parse_resolver(resolver)?;
+                let ipv6_resolver = ipv6_resolver.as_deref().map(dns::parse_resolver).transpose()?;
+                let (ipv4_resolver, default_ipv6_resolver) = match resolver {
+                    SocketAddr::V4(_) => (Some(resolver), None),
+                    SocketAddr::V6(_) => (None, Some(resolver)),
+                };
+                Ok(Box::new(DnsSource::new(
+                    &format!("dns:{}", query),
+                    query,
+                    *record_type,
+                    *class,
+                    ipv4_resolver,
+                    ipv6_resolver.or(default_ipv6_resolver),
+                )))
+            }
+            IpSourceConfig::Stun { servers } => {
+                if servers.is_empty() {
+                    return Err(AppError::ConfigError("STUN servers cannot be empty".to_string()));
+                }
+                Ok(Box::new(StunSource::new(servers.clone())))
+            }
+            IpSourceConfig::Gateway { protocol, gateway, location } => {
+                let gateway = gateway.as_deref().map(gateway::parse_gateway).transpose()?;
+                Ok(Box::new(GatewaySource::new(*protocol, gateway, location.clone())))
+            }
+            IpSourceConfig::Named(name) => Self::parse(name)?.build(),
+        }
+    }
+
+    /// 解析簡寫形式的來源
+    ///
+    /// # 參數
+    ///
+    /// - `name`: 來源名稱、`interface:<介面名稱>`、`stun:<host:port>` 或 URL
+    ///
+    /// # 返回
+    ///
+    /// - `Result<IpSourceConfig, AppError>`: 成功時返回完整形式的配置，失敗時返回錯誤
+    pub fn parse(name: &str) -> Result<Self, AppError> {
+        let name = name.trim();
+        match name {
+            "ipify" => Ok(IpSourceConfig::Ipify),
+            "icanhazip" => Ok(IpSourceConfig::Icanhazip),
+            "cloudflare" => Ok(IpSourceConfig::Cloudflare),
+            "opendns" => Ok(IpSourceConfig::Opendns),
+            "cloudflare_dns" => Ok(IpSourceConfig::CloudflareDns),
+            "stun" => Ok(IpSourceConfig::Stun { servers: stun::default_servers() }),
+            "gateway" | "upnp" | "natpmp" | "pcp" => Ok(IpSourceConfig::Gateway {
+                protocol: match name {
+                    "upnp" => gateway::GatewayProtocol::Upnp,
+                    "natpmp" => gateway::GatewayProtocol::Natpmp,
+                    "pcp" => gateway::GatewayProtocol::Pcp,
+                    _ => gateway::GatewayProtocol::Auto,
+                },
+                gateway: None,
+                location: None,
+            }),
+            _ if name.starts_with("stun:") => Ok(IpSourceConfig::Stun {
+                servers: vec![name.trim_start_matches("stun:").to_string()],
+            }),
+            _ if name.starts_with("interface:") => Ok(IpSourceConfig::Interface {
+                interface: name.trim_start_matches("interface:").to_string(),
+            }),
+            _ if name.starts_with("http://") || name.starts_with("https://") => Ok(IpSourceConfig::Custom {
+                url: name.to_string(),
+                ipv6_url: None,
+                key: None,
+            }),
+            _ => Err(AppError::ConfigError(format!("Unknown IP source: {}", name)))
+        }
+    }
+}
+
+/// 將 IP 來源返回的文字解析為指定版本的 IP 地址
+///
+/// # 參數
+///
+/// - `raw`: 來源返回的文字（會去除前後空白）
+/// - `version`: 預期的 IP 版本
+///
+/// # 返回
+///
+/// - `Result<IpAddr, AppError>`: 成功時返回 IP 地址，失敗時返回錯誤
+///
+/// # 錯誤
+///
+/// 當以下情況發生時返回 `AppError::InvalidIpResponse`：
+/// - 內容不是有效的 IP 地址（例如 captive portal 頁面或錯誤訊息）
+/// - 地址版本與預期不符
+pub fn parse_ip(raw: &str, version: IpVersion) -> Result<IpAddr, AppError> {
+    let value = raw.trim();
+    let ip: IpAddr = value.parse().map_err(|_| {
+        let excerpt: String = value.chars().take(MAX_RESPONSE_EXCERPT).collect();
+        AppError::InvalidIpResponse(format!("not an IP address: {:?}", excerpt))
+    })?;
+
+    check_version(ip, version)
+}
+
+/// 檢查 IP 地址是否為預期的版本
+///
+/// # 參數
+///
+/// - `ip`: IP 地址
+/// - `version`: 預期的 IP 版本
+///
+/// # 返回
+///
+/// - `Result<IpAddr, AppError>`: 版本相符時返回原地址，否則返回 `AppError::InvalidIpResponse`
+pub fn check_version(ip: IpAddr, version: IpVersion) -> Result<IpAddr, AppError> {
+    match (version, ip) {
+        (IpVersion::V4, IpAddr::V4(_)) | (IpVersion::V6, IpAddr::V6(_)) => Ok(ip),
+        _ => Err(AppError::InvalidIpResponse(format!("expected {} address, got {}", version, ip))),
+    }
+}
+
+/// 設置全域 IP 偵測設置
+///
+/// 只在第一次呼叫時生效，應於啟動時根據應用程式設置呼叫
+///
+/// # 參數
+///
+/// - `settings`: IP 偵測設置
+pub fn configure(settings: IpSettings) {
+    if SETTINGS.set(settings).is_err() {
+        warn!("IP detection already configured, ignoring new configuration");
+    }
+}
+
+/// 取得全域 IP 偵測設置，未設置時使用預設值
+pub fn settings() -> &'static IpSettings {
+    SETTINGS.get_or_init(IpSettings::default)
+}
+
+/// 取得全域 IP 觀察者
+///
+/// 快取有效時間由設置中的 `cache_ttl` 決定
+pub fn observer() -> &'static IpObserver {
+    OBSERVER.get_or_init(|| IpObserver::new(Duration::from_secs(settings().cache_ttl)))
+}
+
+/// 內建的預設 IP 來源
+pub fn builtin_sources() -> Vec<IpSourceConfig> {
+    DEFAULT_SOURCE_NAMES.iter().map(|name| IpSourceConfig::Named(name.to_string())).collect()
+}
+
+/// 取得全域預設的 IP 來源配置
+pub fn default_sources() -> Vec<IpSourceConfig> {
+    settings().sources.clone()
+}
+
+/// 根據來源配置建立備援鏈
+///
+/// # 參數
+///
+/// - `sources`: 依優先順序排列的來源配置
+///
+/// # 返回
+///
+/// - `Result<IpSourceChain, AppError>`: 成功時返回備援鏈，失敗時返回錯誤
+pub fn build_chain(sources: &[IpSourceConfig]) -> Result<IpSourceChain, AppError> {
+    Ok(IpSourceChain::new(build_sources(sources)?))
+}
+
+/// 根據來源配置建立 IP 偵測器
+///
+/// 設置 `quorum` 時同時查詢所有來源並要求至少 `quorum` 個來源一致，
+/// 否則依序嘗試來源直到成功
+///
+/// # 參數
+///
+/// - `sources`: 來源配置
+/// - `quorum`: 共識所需的一致來源數（可選）
+///
+/// # 返回
+///
+/// - `Result<Box<dyn IpSource>, AppError>`: 成功時返回 IP 偵測器，失敗時返回錯誤
+pub fn build_detector(sources: &[IpSourceConfig], quorum: Option<usize>) -> Result<Box<dyn IpSource>, AppError> {
+    match quorum {
+        Some(quorum) => Ok(Box::new(QuorumSource::new(build_sources(sources)?, quorum)?)),
+        None => Ok(Box::new(build_chain(sources)?)),
+    }
+}
+
+/// 根據來源配置建立所有 IP 來源
+fn build_sources(sources: &[IpSourceConfig]) -> Result<Vec<Box<dyn IpSource>>, AppError> {
+    sources
+        .iter()
+        .map(IpSourceConfig::build)
+        .collect()
+}
+
+/// 獲取當前 IPv4 地址
+///
+/// 使用全域 IP 偵測設置，透過共用的 IP 觀察者查詢
+///
+/// # 返回
+///
+/// - `Result<Ipv4Addr, AppError>`: 成功時返回 IPv4 地址，失敗時返回錯誤
+///
+/// # 錯誤
+///
+/// 當以下情況發生時返回錯誤：
+/// - 所有來源的請求都失敗，或未達成共識
+/// - 來源返回的內容不是有效的 IPv4 地址
+/// - 來源配置無效
+pub async fn fetch_ipv4() -> Result<Ipv4Addr, AppError> {
+    let settings = settings();
+    match observer().observe(&settings.sources, settings.quorum, IpVersion::V4, None).await? {
+        IpAddr::V4(ip) => Ok(ip),
+        ip => Err(AppError::InvalidIpResponse(format!("expected IPv4 address, got {}", ip))),
+    }
+}
+
+/// 獲取當前 IPv6 地址
+///
+/// 使用全域 IP 偵測設置，透過共用的 IP 觀察者查詢
+///
+/// # 返回
+///
+/// - `Result<Ipv6Addr, AppError>`: 成功時返回 IPv6 地址，失敗時返回錯誤
+///
+/// # 錯誤
+///
+/// 當以下情況發生時返回錯誤：
+/// - 所有來源的請求都失敗，或未達成共識
+/// - 來源返回的內容不是有效的 IPv6 地址
+/// - 來源配置無效
+pub async fn fetch_ipv6() -> Result<Ipv6Addr, AppError> {
+    let settings = settings();
+    match observer().observe(&settings.sources, settings.quorum, IpVersion::V6, None).await? {
+        IpAddr::V6(ip) => Ok(ip),
+        ip => Err(AppError::InvalidIpResponse(format!("expected IPv6 address, got {}", ip))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ip_trims_whitespace() {
+        assert_eq!(parse_ip("  198.51.100.7\r\n", IpVersion::V4).unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+        assert_eq!(parse_ip("\t2001:db8::7\n", IpVersion::V6).unwrap(), "2001:db8::7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn parse_ip_rejects_html_body() {
+        let body = format!("<!DOCTYPE html><html><head><title>Login</title></head><body>{}</body></html>", "x".repeat(200));
+        let error = parse_ip(&body, IpVersion::V4).unwrap_err();
+        let AppError::InvalidIpResponse(message) = &error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert!(message.starts_with("not an IP address: \"<!DOCTYPE html>"), "{}", message);
+        // 錯誤訊息只保留響應開頭
+        assert!(message.len() < 100, "{}", message);
+    }
+
+    #[test]
+    fn parse_ip_rejects_wrong_family() {
+        assert!(matches!(parse_ip("2001:db8::7", IpVersion::V4), Err(AppError::InvalidIpResponse(_))));
+        assert!(matches!(parse_ip("198.51.100.7", IpVersion::V6), Err(AppError::InvalidIpResponse(_))));
+    }
+
+    #[test]
+    fn check_version_matches_family() {
+        let v4: IpAddr = "198.51.100.7".parse().unwrap();
+        let v6: IpAddr = "2001:db8::7".parse().unwrap();
+        assert_eq!(check_version(v4, IpVersion::V4).unwrap(), v4);
+        assert_eq!(check_version(v6, IpVersion::V6).unwrap(), v6);
+        assert!(matches!(check_version(v4, IpVersion::V6), Err(AppError::InvalidIpResponse(_))));
+        assert!(matches!(check_version(v6, IpVersion::V4), Err(AppError::InvalidIpResponse(_))));
+    }
+}
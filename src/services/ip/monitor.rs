@@ -0,0 +1,90 @@
+use tokio::time::Instant;
+use crate::error::AppError;
+use super::IpVersion;
+#[cfg(target_os = "linux")]
+use log::{debug, info};
+
+/// 合併連續地址變更通知的等待時間
+#[cfg(target_os = "linux")]
+const DEBOUNCE: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 本機地址變更監聽器
+///
+/// 在 Linux 上訂閱 rtnetlink 的 RTM_NEWADDR / RTM_DELADDR 通知，
+/// 讓 DDNS 服務在 PPPoE 重新撥號等情況下立即重新檢查 IP，而不必等到下一次輪詢
+pub struct AddressMonitor {
+    #[cfg(target_os = "linux")]
+    subscription: super::netlink::AddressSubscription,
+    #[cfg(target_os = "linux")]
+    version: IpVersion,
+}
+
+impl AddressMonitor {
+    /// 創建新的地址變更監聽器
+    ///
+    /// # 參數
+    ///
+    /// - `version`: 要監聽的 IP 版本
+    ///
+    /// # 返回
+    ///
+    /// - `Result<Self, AppError>`: 成功時返回監聽器，不支援的平台或訂閱失敗時返回錯誤
+    #[cfg(target_os = "linux")]
+    pub fn new(version: IpVersion) -> Result<Self, AppError> {
+        let subscription = super::netlink::AddressSubscription::new(version)
+            .map_err(|e| AppError::InternalError(format!("Failed to subscribe to netlink address events: {}", e)))?;
+
+        Ok(Self { subscription, version })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(_version: IpVersion) -> Result<Self, AppError> {
+        Err(AppError::ConfigError("Address change notifications are only supported on Linux".to_string()))
+    }
+
+    /// 等待下一次可發布地址的變更
+    ///
+    /// 忽略 link-local、臨時地址等不會被發布的地址，
+    /// 並合併短時間內連續發生的通知
+    ///
+    /// # 返回
+    ///
+    /// - `Result<Instant, AppError>`: 發生變更時返回第一個通知的時間，監聽失敗時返回錯誤
+    #[cfg(target_os = "linux")]
+    pub async fn changed(&mut self) -> Result<Instant, AppError> {
+        loop {
+            let event = self.next_event().await?;
+            match event {
+                Some(address) if !super::interface::is_usable(&address) => {
+                    debug!("Ignoring {} address change for {}", self.version, address.address);
+                    continue;
+                }
+                Some(address) => info!("{} address change detected: {}", self.version, address.address),
+                None => info!("{} address change notifications overflowed", self.version),
+            }
+            break;
+        }
+        let changed_at = Instant::now();
+
+        // 等待地址穩定，期間的其他通知一併合併
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(DEBOUNCE) => return Ok(changed_at),
+                event = self.next_event() => { event?; }
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub async fn changed(&mut self) -> Result<Instant, AppError> {
+        std::future::pending().await
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn next_event(&mut self) -> Result<Option<super::netlink::InterfaceAddress>, AppError> {
+        self.subscription
+            .next()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Netlink address subscription failed: {}", e)))
+    }
+}
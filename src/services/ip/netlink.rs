@@ -0,0 +1,434 @@
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use tokio::io::unix::AsyncFd;
+use super::IpVersion;
+
+/// nlmsghdr 長度
+const NLMSG_HDR_LEN: usize = 16;
+/// ifaddrmsg 長度
+const IFADDRMSG_LEN: usize = 8;
+/// rtattr 標頭長度
+const RTATTR_HDR_LEN: usize = 4;
+/// 接收緩衝區大小
+const RECV_BUFFER_LEN: usize = 16 * 1024;
+
+/// 介面上的一個地址（來自 RTM_NEWADDR）
+///
+/// # 欄位
+///
+/// - `index`: 介面索引
+/// - `address`: 地址
+/// - `prefix_len`: 前綴長度
+/// - `scope`: 地址範圍（`RT_SCOPE_*`）
+/// - `flags`: 地址旗標（`IFA_F_*`，包含 `IFA_FLAGS` 擴充旗標）
+#[derive(Debug, Clone)]
+pub struct InterfaceAddress {
+    pub index: u32,
+    pub address: IpAddr,
+    pub prefix_len: u8,
+    pub scope: u8,
+    pub flags: u32,
+}
+
+/// 將長度對齊到 4 位元組（NLMSG_ALIGN / RTA_ALIGN）
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// 開啟 NETLINK_ROUTE socket
+///
+/// # 參數
+///
+/// - `groups`: 訂閱的多播群組（`RTMGRP_*`），0 表示不訂閱
+/// - `nonblocking`: 是否以非阻塞模式開啟
+fn open_socket(groups: u32, nonblocking: bool) -> io::Result<OwnedFd> {
+    let mut flags = libc::SOCK_RAW | libc::SOCK_CLOEXEC;
+    if nonblocking {
+        flags |= libc::SOCK_NONBLOCK;
+    }
+
+    // SAFETY: 直接呼叫 socket(2)，返回值在下方檢查
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, flags, libc::NETLINK_ROUTE) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: fd 為剛建立且有效的檔案描述符，由 OwnedFd 負責關閉
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: sockaddr_nl 為純資料結構，全零為合法值
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = groups;
+
+    // SAFETY: addr 為有效的 sockaddr_nl，長度正確
+    let ret = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(fd)
+}
+
+/// 從 netlink socket 接收一個資料包
+fn recv(fd: &OwnedFd, buf: &mut [u8]) -> io::Result<usize> {
+    // SAFETY: buf 為有效的可寫緩衝區
+    let len = unsafe { libc::recv(fd.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(len as usize)
+}
+
+/// 取得介面名稱對應的索引
+///
+/// # 參數
+///
+/// - `name`: 介面名稱（例如 `eth0`）
+pub fn interface_index(name: &str) -> io::Result<u32> {
+    let c_name = CString::new(name).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"))?;
+    // SAFETY: c_name 為有效的 NUL 結尾字串
+    let index = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+    if index == 0 {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("interface {} not found", name)));
+    }
+    Ok(index)
+}
+
+/// 列出系統上指定版本的所有介面地址（RTM_GETADDR dump）
+///
+/// # 參數
+///
+/// - `version`: IP 版本
+///
+/// # 返回
+///
+/// - `io::Result<Vec<InterfaceAddress>>`: 成功時返回地址列表，失敗時返回錯誤
+pub fn dump_addresses(version: IpVersion) -> io::Result<Vec<InterfaceAddress>> {
+    let fd = open_socket(0, false)?;
+
+    let family = match version {
+        IpVersion::V4 => libc::AF_INET,
+        IpVersion::V6 => libc::AF_INET6,
+    } as u8;
+
+    let mut request = Vec::with_capacity(NLMSG_HDR_LEN + IFADDRMSG_LEN);
+    request.extend_from_slice(&((NLMSG_HDR_LEN + IFADDRMSG_LEN) as u32).to_ne_bytes());
+    request.extend_from_slice(&libc::RTM_GETADDR.to_ne_bytes());
+    request.extend_from_slice(&((libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16).to_ne_bytes());
+    request.extend_from_slice(&1u32.to_ne_bytes()); // seq
+    request.extend_from_slice(&0u32.to_ne_bytes()); // pid
+    request.extend_from_slice(&[family, 0, 0, 0]);
+    request.extend_from_slice(&0u32.to_ne_bytes()); // ifa_index
+
+    // SAFETY: request 為有效的緩衝區
+    let sent = unsafe { libc::send(fd.as_raw_fd(), request.as_ptr() as *const libc::c_void, request.len(), 0) };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut addresses = Vec::new();
+    let mut buf = vec![0u8; RECV_BUFFER_LEN];
+
+    loop {
+        let len = recv(&fd, &mut buf)?;
+        let mut done = false;
+
+        for (msg_type, payload) in messages(&buf[..len]) {
+            match msg_type as libc::c_int {
+                libc::NLMSG_DONE => done = true,
+                libc::NLMSG_ERROR => {
+                    let errno = payload.get(..4)
+                        .map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                        .unwrap_or(0);
+                    if errno != 0 {
+                        return Err(io::Error::from_raw_os_error(-errno));
+                    }
+                    done = true;
+                }
+                _ if msg_type == libc::RTM_NEWADDR => {
+                    if let Some(address) = parse_address(payload) {
+                        addresses.push(address);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if done || len == 0 {
+            break;
+        }
+    }
+
+    Ok(addresses)
+}
+
+/// 地址變更訂閱
+///
+/// 訂閱 RTMGRP_IPV4_IFADDR / RTMGRP_IPV6_IFADDR 多播群組，
+/// 接收核心發出的 RTM_NEWADDR / RTM_DELADDR 通知
+pub struct AddressSubscription {
+    fd: AsyncFd<OwnedFd>,
+    buf: Vec<u8>,
+}
+
+impl AddressSubscription {
+    /// 訂閱指定版本的地址變更
+    ///
+    /// 必須在 tokio 執行環境中呼叫
+    ///
+    /// # 參數
+    ///
+    /// - `version`: IP 版本
+    pub fn new(version: IpVersion) -> io::Result<Self> {
+        let groups = match version {
+            IpVersion::V4 => libc::RTMGRP_IPV4_IFADDR,
+            IpVersion::V6 => libc::RTMGRP_IPV6_IFADDR,
+        } as u32;
+
+        Ok(Self {
+            fd: AsyncFd::new(open_socket(groups, true)?)?,
+            buf: vec![0u8; RECV_BUFFER_LEN],
+        })
+    }
+
+    /// 等待下一個地址變更通知
+    ///
+    /// # 返回
+    ///
+    /// - `io::Result<Option<InterfaceAddress>>`: 新增或刪除的地址；通知佇列溢位時無法得知具體地址，返回 `None`
+    pub async fn next(&mut self) -> io::Result<Option<InterfaceAddress>> {
+        loop {
+            let mut guard = self.fd.readable().await?;
+            let buf = &mut self.buf;
+
+            let len = match guard.try_io(|fd| recv(fd.get_ref(), buf)) {
+                Ok(Ok(len)) => len,
+                // 接收緩衝區溢位表示有通知遺失，視為發生變更
+                Ok(Err(e)) if e.raw_os_error() == Some(libc::ENOBUFS) => return Ok(None),
+                Ok(Err(e)) => return Err(e),
+                Err(_would_block) => continue,
+            };
+
+            let changed = messages(&buf[..len])
+                .into_iter()
+                .filter(|(msg_type, _)| *msg_type == libc::RTM_NEWADDR || *msg_type == libc::RTM_DELADDR)
+                .find_map(|(_, payload)| parse_address(payload));
+
+            if let Some(address) = changed {
+                return Ok(Some(address));
+            }
+        }
+    }
+}
+
+/// 拆分資料包中的 netlink 訊息
+///
+/// # 返回
+///
+/// 每個訊息的類型與內容（不含 nlmsghdr）
+fn messages(mut data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut result = Vec::new();
+
+    while data.len() >= NLMSG_HDR_LEN {
+        let len = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let msg_type = u16::from_ne_bytes([data[4], data[5]]);
+        if len < NLMSG_HDR_LEN || len > data.len() {
+            break;
+        }
+        result.push((msg_type, &data[NLMSG_HDR_LEN..len]));
+        data = &data[align(len).min(data.len())..];
+    }
+
+    result
+}
+
+/// 解析 RTM_NEWADDR / RTM_DELADDR 訊息內容
+///
+/// # 參數
+///
+/// - `payload`: ifaddrmsg 與其後的 rtattr
+fn parse_address(payload: &[u8]) -> Option<InterfaceAddress> {
+    if payload.len() < IFADDRMSG_LEN {
+        return None;
+    }
+
+    let family = payload[0] as libc::c_int;
+    let prefix_len = payload[1];
+    let mut flags = payload[2] as u32;
+    let scope = payload[3];
+    let index = u32::from_ne_bytes([payload[4], payload[5], payload[6], payload[7]]);
+
+    let mut address = None;
+    let mut local = None;
+    let mut attrs = &payload[IFADDRMSG_LEN..];
+
+    while attrs.len() >= RTATTR_HDR_LEN {
+        let len = u16::from_ne_bytes([attrs[0], attrs[1]]) as usize;
+        let attr_type = u16::from_ne_bytes([attrs[2], attrs[3]]);
+        if len < RTATTR_HDR_LEN || len > attrs.len() {
+            break;
+        }
+        let data = &attrs[RTATTR_HDR_LEN..len];
+
+        match attr_type {
+            libc::IFA_ADDRESS => address = parse_ip(family, data),
+            libc::IFA_LOCAL => local = parse_ip(family, data),
+            libc::IFA_FLAGS if data.len() >= 4 => flags = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]),
+            _ => {}
+        }
+
+        attrs = &attrs[align(len).min(attrs.len())..];
+    }
+
+    // 點對點鏈路（如 PPPoE）的 IFA_ADDRESS 為對端地址，本地地址位於 IFA_LOCAL
+    Some(InterfaceAddress {
+        index,
+        address: local.or(address)?,
+        prefix_len,
+        scope,
+        flags,
+    })
+}
+
+/// 根據地址族解析地址屬性
+fn parse_ip(family: libc::c_int, data: &[u8]) -> Option<IpAddr> {
+    match family {
+        libc::AF_INET => {
+            let octets: [u8; 4] = data.get(..4)?.try_into().ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        libc::AF_INET6 => {
+            let octets: [u8; 16] = data.get(..16)?.try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 構建 rtattr，內容依 4 位元組對齊補齊
+    fn attr(attr_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut attr = Vec::new();
+        attr.extend_from_slice(&((RTATTR_HDR_LEN + data.len()) as u16).to_ne_bytes());
+        attr.extend_from_slice(&attr_type.to_ne_bytes());
+        attr.extend_from_slice(data);
+        attr.resize(align(attr.len()), 0);
+        attr
+    }
+
+    /// 構建 ifaddrmsg 與其後的屬性
+    fn ifaddrmsg(family: libc::c_int, flags: u8, index: u32, attrs: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload = vec![family as u8, 64, flags, libc::RT_SCOPE_UNIVERSE];
+        payload.extend_from_slice(&index.to_ne_bytes());
+        attrs.iter().for_each(|attr| payload.extend_from_slice(attr));
+        payload
+    }
+
+    /// 構建 netlink 訊息（不補齊結尾）
+    fn message(msg_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(&((NLMSG_HDR_LEN + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&msg_type.to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(payload);
+        message
+    }
+
+    #[test]
+    fn prefers_local_address_on_point_to_point_links() {
+        // PPPoE：IFA_ADDRESS 為對端地址，IFA_LOCAL 為本地地址
+        let payload = ifaddrmsg(libc::AF_INET, 0, 3, &[
+            attr(libc::IFA_ADDRESS, &[203, 0, 113, 1]),
+            attr(libc::IFA_LOCAL, &[198, 51, 100, 7]),
+        ]);
+        let address = parse_address(&payload).unwrap();
+        assert_eq!(address.address, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)));
+        assert_eq!(address.index, 3);
+        assert_eq!(address.prefix_len, 64);
+
+        let payload = ifaddrmsg(libc::AF_INET6, 0, 3, &[attr(libc::IFA_ADDRESS, &"2001:db8::7".parse::<Ipv6Addr>().unwrap().octets())]);
+        assert_eq!(parse_address(&payload).unwrap().address, "2001:db8::7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn extended_flags_override_header_flags() {
+        let flags = libc::IFA_F_MANAGETEMPADDR | libc::IFA_F_NOPREFIXROUTE;
+        let payload = ifaddrmsg(libc::AF_INET6, libc::IFA_F_TEMPORARY as u8, 2, &[
+            attr(libc::IFA_FLAGS, &flags.to_ne_bytes()),
+            attr(libc::IFA_ADDRESS, &"2001:db8::7".parse::<Ipv6Addr>().unwrap().octets()),
+        ]);
+        assert_eq!(parse_address(&payload).unwrap().flags, flags);
+
+        // 長度不足的 IFA_FLAGS 被忽略
+        let payload = ifaddrmsg(libc::AF_INET6, libc::IFA_F_TEMPORARY as u8, 2, &[
+            attr(libc::IFA_FLAGS, &[0, 0]),
+            attr(libc::IFA_ADDRESS, &"2001:db8::7".parse::<Ipv6Addr>().unwrap().octets()),
+        ]);
+        assert_eq!(parse_address(&payload).unwrap().flags, libc::IFA_F_TEMPORARY);
+    }
+
+    #[test]
+    fn aligns_attributes() {
+        // IFA_LABEL "ppp0\0" 長 9 位元組，補齊到 12 位元組
+        let label = attr(libc::IFA_LABEL, b"ppp0\0");
+        assert_eq!(label.len(), 12);
+        let payload = ifaddrmsg(libc::AF_INET, 0, 3, &[label, attr(libc::IFA_LOCAL, &[198, 51, 100, 7])]);
+        assert_eq!(parse_address(&payload).unwrap().address, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)));
+    }
+
+    #[test]
+    fn stops_at_truncated_attributes() {
+        let payload = ifaddrmsg(libc::AF_INET, 0, 3, &[attr(libc::IFA_LOCAL, &[198, 51, 100, 7])]);
+        assert!(parse_address(&payload[..payload.len() - 1]).is_none());
+        assert!(parse_address(&payload[..IFADDRMSG_LEN - 1]).is_none());
+
+        // 截斷的屬性之前已解析的地址仍然有效
+        let payload = ifaddrmsg(libc::AF_INET, 0, 3, &[
+            attr(libc::IFA_ADDRESS, &[198, 51, 100, 7]),
+            attr(libc::IFA_LOCAL, &[192, 0, 2, 1]),
+        ]);
+        assert_eq!(parse_address(&payload[..payload.len() - 2]).unwrap().address, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)));
+
+        // 地址長度不符的屬性
+        let payload = ifaddrmsg(libc::AF_INET6, 0, 3, &[attr(libc::IFA_ADDRESS, &[198, 51, 100, 7])]);
+        assert!(parse_address(&payload).is_none());
+    }
+
+    #[test]
+    fn splits_aligned_messages() {
+        let first = message(libc::RTM_NEWADDR, &[1, 2, 3, 4, 5]);
+        let second = message(libc::RTM_DELADDR, &[6, 7, 8, 9]);
+        let mut data = first.clone();
+        data.resize(align(first.len()), 0);
+        data.extend_from_slice(&second);
+
+        let messages = messages(&data);
+        assert_eq!(messages, vec![(libc::RTM_NEWADDR, &[1, 2, 3, 4, 5][..]), (libc::RTM_DELADDR, &[6, 7, 8, 9][..])]);
+    }
+
+    #[test]
+    fn stops_at_truncated_messages() {
+        let mut data = message(libc::RTM_NEWADDR, &[1, 2, 3, 4]);
+        let second = message(libc::RTM_DELADDR, &[5, 6, 7, 8]);
+        data.extend_from_slice(&second[..second.len() - 1]);
+        assert_eq!(messages(&data), vec![(libc::RTM_NEWADDR, &[1, 2, 3, 4][..])]);
+
+        // 長度小於標頭的訊息
+        let mut data = message(libc::RTM_NEWADDR, &[]);
+        data[..4].copy_from_slice(&8u32.to_ne_bytes());
+        assert!(messages(&data).is_empty());
+        assert!(messages(&data[..NLMSG_HDR_LEN - 1]).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use async_trait::async_trait;
+use futures::future::join_all;
+use crate::error::AppError;
+use super::IpVersion;
+use super::source::IpSource;
+use log::{debug, warn};
+
+/// 多來源共識 IP 來源
+///
+/// 同時查詢所有來源，只有當至少 `quorum` 個來源返回相同地址時才接受，
+/// 避免單一異常的回顯服務改寫 DNS 記錄
+///
+/// # 欄位
+///
+/// - `sources`: 參與投票的 IP 來源
+/// - `quorum`: 接受地址所需的最少一致來源數
+pub struct QuorumSource {
+    sources: Vec<Box<dyn IpSource>>,
+    quorum: usize,
+}
+
+impl QuorumSource {
+    /// 創建新的共識來源
+    ///
+    /// # 參數
+    ///
+    /// - `sources`: 參與投票的 IP 來源
+    /// - `quorum`: 接受地址所需的最少一致來源數
+    ///
+    /// # 返回
+    ///
+    /// - `Result<Self, AppError>`: 成功時返回共識來源，`quorum` 為 0 或大於來源數時返回錯誤
+    pub fn new(sources: Vec<Box<dyn IpSource>>, quorum: usize) -> Result<Self, AppError> {
+        if quorum == 0 || quorum > sources.len() {
+            return Err(AppError::ConfigError(format!(
+                "IP quorum must be between 1 and the number of sources ({}), got {}", sources.len(), quorum
+            )));
+        }
+
+        Ok(Self { sources, quorum })
+    }
+}
+
+#[async_trait]
+impl IpSource for QuorumSource {
+    fn name(&self) -> &str {
+        "quorum"
+    }
+
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        let results = join_all(self.sources.iter().map(|source| source.fetch(version))).await;
+
+        let mut votes: HashMap<IpAddr, Vec<&str>> = HashMap::new();
+        let mut failures = Vec::new();
+
+        for (source, result) in self.sources.iter().zip(results) {
+            match result {
+                Ok(ip) => {
+                    debug!("IP source {} reported {} address: {}", source.name(), version, ip);
+                    votes.entry(ip).or_default().push(source.name());
+                }
+                Err(e) => {
+                    warn!("IP source {} failed for {}: {}", source.name(), version, e);
+                    failures.push(format!("{}: {}", source.name(), e));
+                }
+            }
+        }
+
+        let mut agreed: Vec<_> = votes
+            .iter()
+            .filter(|(_, voters)| voters.len() >= self.quorum)
+            .collect();
+
+        if agreed.len() == 1 {
+            let (ip, voters) = agreed.remove(0);
+            debug!("{} address {} accepted by {}/{} sources", version, ip, voters.len(), self.sources.len());
+            return Ok(*ip);
+        }
+
+        let summary = votes
+            .iter()
+            .map(|(ip, voters)| format!("{} ({})", ip, voters.join(", ")))
+            .chain(failures)
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        Err(AppError::ExternalServiceError(format!(
+            "No {} address reached quorum of {}/{}: {}", version, self.quorum, self.sources.len(), summary
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 返回固定結果的 IP 來源
+    struct StubSource {
+        name: &'static str,
+        result: Result<IpAddr, AppError>,
+    }
+
+    #[async_trait]
+    impl IpSource for StubSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn fetch(&self, _version: IpVersion) -> Result<IpAddr, AppError> {
+            self.result.clone()
+        }
+    }
+
+    /// 建立由指定結果組成的共識來源
+    fn quorum(results: Vec<(&'static str, Result<IpAddr, AppError>)>, quorum: usize) -> QuorumSource {
+        let sources = results
+            .into_iter()
+            .map(|(name, result)| Box::new(StubSource { name, result }) as Box<dyn IpSource>)
+            .collect();
+        QuorumSource::new(sources, quorum).unwrap()
+    }
+
+    fn ip(address: &str) -> Result<IpAddr, AppError> {
+        Ok(address.parse().unwrap())
+    }
+
+    fn failure() -> Result<IpAddr, AppError> {
+        Err(AppError::ExternalServiceError("timeout".to_string()))
+    }
+
+    #[tokio::test]
+    async fn accepts_address_reaching_quorum() {
+        let source = quorum(vec![
+            ("a", ip("198.51.100.7")),
+            ("b", ip("203.0.113.9")),
+            ("c", ip("198.51.100.7")),
+            ("d", failure()),
+        ], 2);
+
+        assert_eq!(source.fetch(IpVersion::V4).await.unwrap(), ip("198.51.100.7").unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_tie_between_addresses_reaching_quorum() {
+        let source = quorum(vec![
+            ("a", ip("198.51.100.7")),
+            ("b", ip("203.0.113.9")),
+            ("c", ip("198.51.100.7")),
+            ("d", ip("203.0.113.9")),
+        ], 2);
+
+        let error = source.fetch(IpVersion::V4).await.unwrap_err();
+        let AppError::ExternalServiceError(message) = &error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert!(message.contains("198.51.100.7 (a, c)"), "{}", message);
+        assert!(message.contains("203.0.113.9 (b, d)"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn too_few_successes_is_error() {
+        let source = quorum(vec![
+            ("a", ip("198.51.100.7")),
+            ("b", failure()),
+            ("c", failure()),
+        ], 2);
+
+        let error = source.fetch(IpVersion::V4).await.unwrap_err();
+        let AppError::ExternalServiceError(message) = &error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert!(message.contains("quorum of 2/3"), "{}", message);
+        assert!(message.contains("b: External service error: timeout"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_invalid_quorum() {
+        let sources = || vec![Box::new(StubSource { name: "a", result: failure() }) as Box<dyn IpSource>];
+        assert!(matches!(QuorumSource::new(sources(), 0), Err(AppError::ConfigError(_))));
+        assert!(matches!(QuorumSource::new(sources(), 2), Err(AppError::ConfigError(_))));
+    }
+}
@@ -0,0 +1,175 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::AppError;
+use super::IpVersion;
+use log::{debug, warn};
+
+/// IP 來源 trait
+///
+/// 所有公網 IP 偵測方式（HTTP 回顯服務等）都實作此 trait，
+/// 使其可以被組合成備援鏈
+#[async_trait]
+pub trait IpSource: Send + Sync {
+    /// 來源名稱，用於日誌與錯誤訊息
+    fn name(&self) -> &str;
+
+    /// 取得指定版本的 IP 地址
+    ///
+    /// # 參數
+    ///
+    /// - `version`: IP 版本
+    ///
+    /// # 返回
+    ///
+    /// - `Result<IpAddr, AppError>`: 成功時返回與 `version` 相符的 IP 地址，失敗時返回錯誤
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError>;
+}
+
+/// 依序嘗試的 IP 來源備援鏈
+///
+/// 依照配置順序逐一查詢來源，返回第一個成功的結果
+pub struct IpSourceChain {
+    sources: Vec<Box<dyn IpSource>>,
+}
+
+impl IpSourceChain {
+    /// 創建新的備援鏈
+    ///
+    /// # 參數
+    ///
+    /// - `sources`: 依優先順序排列的 IP 來源
+    pub fn new(sources: Vec<Box<dyn IpSource>>) -> Self {
+        Self { sources }
+    }
+}
+
+#[async_trait]
+impl IpSource for IpSourceChain {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        let mut failures = Vec::new();
+        let mut all_invalid = true;
+        let mut all_unavailable = true;
+
+        for source in &self.sources {
+            match source.fetch(version).await {
+                Ok(ip) => {
+                    debug!("Obtained {} address from {}: {}", version, source.name(), ip);
+                    return Ok(ip);
+                }
+                Err(e) => {
+                    warn!("IP source {} failed for {}: {}, trying next source", source.name(), version, e);
+                    all_invalid &= matches!(e, AppError::InvalidIpResponse(_));
+                    all_unavailable &= matches!(e, AppError::AddressFamilyUnavailable(_));
+                    failures.push(format!("{}: {}", source.name(), e));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            return Err(AppError::ConfigError("No IP sources configured".to_string()));
+        }
+
+        let message = format!("All IP sources failed for {}: {}", version, failures.join("; "));
+
+        // 所有來源都因相同原因失敗時保留專用的錯誤類型
+        if all_invalid {
+            Err(AppError::InvalidIpResponse(message))
+        } else if all_unavailable {
+            Err(AppError::AddressFamilyUnavailable(message))
+        } else {
+            Err(AppError::ExternalServiceError(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// 返回固定結果並記錄查詢順序的 IP 來源
+    struct StubSource {
+        name: &'static str,
+        result: Result<IpAddr, AppError>,
+        calls: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl IpSource for StubSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn fetch(&self, _version: IpVersion) -> Result<IpAddr, AppError> {
+            self.calls.lock().unwrap().push(self.name);
+            self.result.clone()
+        }
+    }
+
+    /// 建立由指定結果組成的備援鏈，返回備援鏈與查詢順序
+    fn stub_chain(results: Vec<(&'static str, Result<IpAddr, AppError>)>) -> (IpSourceChain, Arc<Mutex<Vec<&'static str>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let sources = results
+            .into_iter()
+            .map(|(name, result)| Box::new(StubSource { name, result, calls: calls.clone() }) as Box<dyn IpSource>)
+            .collect();
+        (IpSourceChain::new(sources), calls)
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn returns_first_success_in_order() {
+        let (chain, calls) = stub_chain(vec![
+            ("first", Err(AppError::ExternalServiceError("timeout".to_string()))),
+            ("second", Ok(ip("198.51.100.7"))),
+            ("third", Ok(ip("198.51.100.8"))),
+        ]);
+
+        assert_eq!(chain.fetch(IpVersion::V4).await.unwrap(), ip("198.51.100.7"));
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn aggregates_failures_of_all_sources() {
+        let (chain, calls) = stub_chain(vec![
+            ("first", Err(AppError::ExternalServiceError("timeout".to_string()))),
+            ("second", Err(AppError::InvalidIpResponse("not an IP address".to_string()))),
+        ]);
+
+        let error = chain.fetch(IpVersion::V4).await.unwrap_err();
+        let AppError::ExternalServiceError(message) = &error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert!(message.contains("first: External service error: timeout"), "{}", message);
+        assert!(message.contains("second: Invalid IP response: not an IP address"), "{}", message);
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn keeps_error_type_shared_by_all_failures() {
+        let (chain, _) = stub_chain(vec![
+            ("first", Err(AppError::InvalidIpResponse("html".to_string()))),
+            ("second", Err(AppError::InvalidIpResponse("empty".to_string()))),
+        ]);
+        assert!(matches!(chain.fetch(IpVersion::V4).await, Err(AppError::InvalidIpResponse(_))));
+
+        let (chain, _) = stub_chain(vec![
+            ("first", Err(AppError::AddressFamilyUnavailable("no route".to_string()))),
+            ("second", Err(AppError::AddressFamilyUnavailable("no route".to_string()))),
+        ]);
+        assert!(matches!(chain.fetch(IpVersion::V6).await, Err(AppError::AddressFamilyUnavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn empty_chain_is_config_error() {
+        let (chain, _) = stub_chain(Vec::new());
+        assert!(matches!(chain.fetch(IpVersion::V4).await, Err(AppError::ConfigError(_))));
+    }
+}
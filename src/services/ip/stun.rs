@@ -0,0 +1,347 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use async_trait::async_trait;
+use tokio::net::lookup_host;
+use tokio::time::timeout;
+use crate::error::AppError;
+use super::{family, IpVersion, check_version};
+use super::source::IpSource;
+use log::debug;
+
+/// STUN magic cookie（RFC 5389）
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+/// 部分舊伺服器使用的 XOR-MAPPED-ADDRESS 屬性編號
+const ATTR_XOR_MAPPED_ADDRESS_LEGACY: u16 = 0x8020;
+const HEADER_LEN: usize = 20;
+const FAMILY_IPV4: u8 = 0x01;
+const FAMILY_IPV6: u8 = 0x02;
+/// 每次重傳前的等待時間（RFC 5389 建議的 RTO 倍增）
+const RETRANSMIT_TIMEOUTS: [Duration; 3] = [
+    Duration::from_millis(500),
+    Duration::from_millis(1000),
+    Duration::from_millis(2000),
+];
+
+/// 預設的 STUN 伺服器
+pub fn default_servers() -> Vec<String> {
+    vec![
+        "stun.cloudflare.com:3478".to_string(),
+        "stun.l.google.com:19302".to_string(),
+    ]
+}
+
+/// 透過 STUN Binding Request 取得公網地址的 IP 來源
+///
+/// 使用 UDP，不依賴 HTTP 回顯服務；依序嘗試設定的伺服器直到成功
+///
+/// # 欄位
+///
+/// - `name`: 來源名稱
+/// - `servers`: STUN 伺服器（`host:port`）
+pub struct StunSource {
+    name: String,
+    servers: Vec<String>,
+}
+
+impl StunSource {
+    /// 創建新的 STUN 來源
+    ///
+    /// # 參數
+    ///
+    /// - `servers`: STUN 伺服器（`host:port`）
+    pub fn new(servers: Vec<String>) -> Self {
+        Self {
+            name: "stun".to_string(),
+            servers,
+        }
+    }
+
+    /// 向單一伺服器發送 Binding Request
+    async fn query(&self, server: SocketAddr, version: IpVersion) -> Result<IpAddr, AppError> {
+        let socket = family::bind_udp(version).await?;
+        socket.connect(server)
+            .await
+            .map_err(|e| AppError::AddressFamilyUnavailable(format!("cannot reach {}: {}", server, e)))?;
+
+        let transaction_id: [u8; 12] = rand::random();
+        let request = build_request(&transaction_id);
+        let mut buf = [0u8; 1024];
+
+        for wait in RETRANSMIT_TIMEOUTS {
+            socket.send(&request)
+                .await
+                .map_err(|e| AppError::ExternalServiceError(format!("Failed to send to {}: {}", server, e)))?;
+
+            let received = timeout(wait, async {
+                loop {
+                    let len = socket.recv(&mut buf).await?;
+                    // 忽略交易 ID 不符的封包
+                    if let Some(result) = parse_response(&buf[..len], &transaction_id) {
+                        return Ok::<_, std::io::Error>(result);
+                    }
+                }
+            })
+            .await;
+
+            match received {
+                Ok(Ok(result)) => return result,
+                Ok(Err(e)) => return Err(AppError::ExternalServiceError(format!("Failed to receive from {}: {}", server, e))),
+                Err(_) => debug!("STUN request to {} timed out after {:?}, retransmitting", server, wait),
+            }
+        }
+
+        Err(AppError::ExternalServiceError(format!("STUN server {} did not respond", server)))
+    }
+}
+
+#[async_trait]
+impl IpSource for StunSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, version: IpVersion) -> Result<IpAddr, AppError> {
+        // 主機沒有該地址族時直接返回，不逐一嘗試伺服器
+        family::probe(version).await?;
+
+        let mut failures = Vec::new();
+
+        for server in &self.servers {
+            let addresses = match lookup_host(server.as_str()).await {
+                Ok(addresses) => addresses,
+                Err(e) => {
+                    failures.push(format!("{}: {}", server, e));
+                    continue;
+                }
+            };
+
+            // 只使用與請求版本相同地址族的伺服器地址，確保觀察到的是該版本的公網地址
+            let Some(address) = addresses.into_iter().find(|a| match version {
+                IpVersion::V4 => a.is_ipv4(),
+                IpVersion::V6 => a.is_ipv6(),
+            }) else {
+                failures.push(format!("{}: no {} address", server, version));
+                continue;
+            };
+
+            match self.query(address, version).await {
+                Ok(ip) => return check_version(ip, version),
+                Err(e) => failures.push(format!("{}: {}", server, e)),
+            }
+        }
+
+        Err(AppError::ExternalServiceError(format!("All STUN servers failed: {}", failures.join("; "))))
+    }
+}
+
+/// 構建 Binding Request
+fn build_request(transaction_id: &[u8; 12]) -> Vec<u8> {
+    let mut request = Vec::with_capacity(HEADER_LEN);
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction_id);
+    request
+}
+
+/// 解析 Binding 響應
+///
+/// # 返回
+///
+/// 不是此交易的響應時返回 `None`；否則返回映射地址或錯誤
+fn parse_response(packet: &[u8], transaction_id: &[u8; 12]) -> Option<Result<IpAddr, AppError>> {
+    if packet.len() < HEADER_LEN
+        || packet[4..8] != MAGIC_COOKIE.to_be_bytes()
+        || &packet[8..20] != transaction_id
+    {
+        return None;
+    }
+
+    let msg_type = u16::from_be_bytes([packet[0], packet[1]]);
+    if msg_type != BINDING_SUCCESS {
+        return Some(Err(AppError::ExternalServiceError(format!("STUN error response (type {:#06x})", msg_type))));
+    }
+
+    let length = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    let mut attrs = packet.get(HEADER_LEN..HEADER_LEN + length)?;
+    let mut mapped = None;
+
+    while attrs.len() >= 4 {
+        let attr_type = u16::from_be_bytes([attrs[0], attrs[1]]);
+        let attr_len = u16::from_be_bytes([attrs[2], attrs[3]]) as usize;
+        let value = attrs.get(4..4 + attr_len)?;
+
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS | ATTR_XOR_MAPPED_ADDRESS_LEGACY => {
+                if let Some(ip) = decode_address(value, Some(transaction_id)) {
+                    return Some(Ok(ip));
+                }
+            }
+            ATTR_MAPPED_ADDRESS => mapped = decode_address(value, None),
+            _ => {}
+        }
+
+        // 屬性以 4 位元組對齊
+        let padded = (4 + attr_len + 3) & !3;
+        attrs = attrs.get(padded.min(attrs.len())..)?;
+    }
+
+    Some(mapped.ok_or_else(|| AppError::InvalidIpResponse("STUN response has no mapped address".to_string())))
+}
+
+/// 解碼 (XOR-)MAPPED-ADDRESS 屬性
+///
+/// # 參數
+///
+/// - `value`: 屬性內容
+/// - `transaction_id`: XOR 編碼時使用的交易 ID，`None` 表示未編碼
+fn decode_address(value: &[u8], transaction_id: Option<&[u8; 12]>) -> Option<IpAddr> {
+    let family = *value.get(1)?;
+    let cookie = MAGIC_COOKIE.to_be_bytes();
+
+    match family {
+        FAMILY_IPV4 => {
+            let mut octets: [u8; 4] = value.get(4..8)?.try_into().ok()?;
+            if transaction_id.is_some() {
+                octets.iter_mut().zip(cookie).for_each(|(b, k)| *b ^= k);
+            }
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        FAMILY_IPV6 => {
+            let mut octets: [u8; 16] = value.get(4..20)?.try_into().ok()?;
+            if let Some(transaction_id) = transaction_id {
+                let key = cookie.iter().chain(transaction_id.iter());
+                octets.iter_mut().zip(key).for_each(|(b, k)| *b ^= k);
+            }
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    const TRANSACTION_ID: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    /// 構建 STUN 訊息，屬性依 4 位元組對齊補齊
+    fn message(msg_type: u16, transaction_id: &[u8; 12], attrs: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (attr_type, value) in attrs {
+            body.extend_from_slice(&attr_type.to_be_bytes());
+            body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            body.extend_from_slice(value);
+            body.resize(body.len().next_multiple_of(4), 0);
+        }
+
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&msg_type.to_be_bytes());
+        packet.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        packet.extend_from_slice(transaction_id);
+        packet.extend_from_slice(&body);
+        packet
+    }
+
+    /// XOR-MAPPED-ADDRESS 的內容（RFC 5389 第 15.2 節）
+    fn xor_mapped(ip: IpAddr, port: u16, transaction_id: &[u8; 12]) -> Vec<u8> {
+        let mut key = MAGIC_COOKIE.to_be_bytes().to_vec();
+        key.extend_from_slice(transaction_id);
+        let (family, octets) = match ip {
+            IpAddr::V4(ip) => (FAMILY_IPV4, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (FAMILY_IPV6, ip.octets().to_vec()),
+        };
+
+        let mut value = vec![0, family];
+        value.extend_from_slice(&(port ^ (MAGIC_COOKIE >> 16) as u16).to_be_bytes());
+        value.extend(octets.iter().zip(&key).map(|(b, k)| b ^ k));
+        value
+    }
+
+    #[test]
+    fn decodes_xor_mapped_ipv4() {
+        // 198.51.100.7 與 magic cookie 0x2112A442 逐位元組 XOR
+        let value = vec![0, FAMILY_IPV4, 0x11, 0x2b, 0xe7, 0x21, 0xc0, 0x45];
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_XOR_MAPPED_ADDRESS, value)]);
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap().unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn decodes_xor_mapped_ipv6() {
+        let ip: IpAddr = "2001:db8:85a3::8a2e:370:7334".parse().unwrap();
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_XOR_MAPPED_ADDRESS_LEGACY, xor_mapped(ip, 32853, &TRANSACTION_ID))]);
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap().unwrap(), ip);
+    }
+
+    #[test]
+    fn prefers_xor_mapped_over_mapped_address() {
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[
+            (ATTR_MAPPED_ADDRESS, vec![0, FAMILY_IPV4, 0x80, 0x55, 192, 0, 2, 1]),
+            // 長度不是 4 的倍數的未知屬性，之後的屬性需要對齊
+            (0x8022, b"test".to_vec()),
+            (0x8023, b"odd".to_vec()),
+            (ATTR_XOR_MAPPED_ADDRESS, xor_mapped("198.51.100.7".parse().unwrap(), 32853, &TRANSACTION_ID)),
+        ]);
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap().unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn falls_back_to_mapped_address() {
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[
+            (0x8023, b"odd".to_vec()),
+            (ATTR_MAPPED_ADDRESS, vec![0, FAMILY_IPV4, 0x80, 0x55, 192, 0, 2, 1]),
+        ]);
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap().unwrap(), "192.0.2.1".parse::<IpAddr>().unwrap());
+
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(0x8022, b"test".to_vec())]);
+        assert!(matches!(parse_response(&packet, &TRANSACTION_ID).unwrap(), Err(AppError::InvalidIpResponse(_))));
+    }
+
+    #[test]
+    fn reports_error_response() {
+        // Binding Error Response 與 ERROR-CODE 400
+        let packet = message(0x0111, &TRANSACTION_ID, &[(0x0009, vec![0, 0, 4, 0])]);
+        assert!(matches!(parse_response(&packet, &TRANSACTION_ID).unwrap(), Err(AppError::ExternalServiceError(_))));
+    }
+
+    #[test]
+    fn ignores_other_transactions() {
+        let value = xor_mapped("198.51.100.7".parse().unwrap(), 32853, &TRANSACTION_ID);
+        let packet = message(BINDING_SUCCESS, &[0; 12], &[(ATTR_XOR_MAPPED_ADDRESS, value.clone())]);
+        assert!(parse_response(&packet, &TRANSACTION_ID).is_none());
+
+        // 沒有 magic cookie 的封包（RFC 3489）
+        let mut packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_XOR_MAPPED_ADDRESS, value)]);
+        packet[4] = 0;
+        assert!(parse_response(&packet, &TRANSACTION_ID).is_none());
+        assert!(parse_response(&packet[..HEADER_LEN - 1], &TRANSACTION_ID).is_none());
+    }
+
+    #[tokio::test]
+    async fn fetches_from_loopback_responder() {
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = responder.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            let (len, peer) = responder.recv_from(&mut buf).await.unwrap();
+            assert_eq!(len, HEADER_LEN);
+            assert_eq!(buf[..8], [0x00, 0x01, 0x00, 0x00, 0x21, 0x12, 0xa4, 0x42]);
+            let transaction_id: [u8; 12] = buf[8..20].try_into().unwrap();
+
+            // 先回應其他交易，應被忽略
+            let other = message(BINDING_SUCCESS, &[0; 12], &[(ATTR_XOR_MAPPED_ADDRESS, xor_mapped("192.0.2.1".parse().unwrap(), 1, &[0; 12]))]);
+            responder.send_to(&other, peer).await.unwrap();
+            let value = xor_mapped("198.51.100.7".parse().unwrap(), peer.port(), &transaction_id);
+            responder.send_to(&message(BINDING_SUCCESS, &transaction_id, &[(ATTR_XOR_MAPPED_ADDRESS, value)]), peer).await.unwrap();
+        });
+
+        let source = StunSource::new(vec![address.to_string()]);
+        assert_eq!(source.fetch(IpVersion::V4).await.unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+}
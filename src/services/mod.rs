@@ -1,2 +1,6 @@
+pub mod http_client;
 pub mod ip;
+pub mod rate_limit;
+pub mod retry;
+pub mod state;
 pub mod ddns;
\ No newline at end of file
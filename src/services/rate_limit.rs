@@ -0,0 +1,242 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use tokio::time::{sleep_until, Duration, Instant};
+use log::{debug, info, warn};
+
+/// Cloudflare API 在 `REQUEST_WINDOW` 內允許的請求數
+const REQUEST_LIMIT: usize = 1200;
+
+/// 計算請求數的時間窗口
+const REQUEST_WINDOW: Duration = Duration::from_secs(300);
+
+/// 429 響應沒有 `Retry-After` 標頭時的等待時間
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// 以帳號（API 令牌或電子郵件）索引的共享限制器
+static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+/// Cloudflare API 請求頻率限制器
+///
+/// 同一個帳號（API 令牌或 Global API Key 的電子郵件）的所有請求共用一個限制器：在滑動窗口內最多發送 `REQUEST_LIMIT` 個請求，
+/// 收到 429 響應後暫停所有請求直到 `Retry-After` 指定的時間
+///
+/// # 欄位
+///
+/// - `state`: 限制器狀態
+pub struct RateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+/// 限制器狀態
+///
+/// # 欄位
+///
+/// - `sent`: 窗口內已發送請求的時間，依時間排序
+/// - `paused_until`: 因 429 響應暫停請求的結束時間（可選）
+#[derive(Default)]
+struct LimiterState {
+    sent: VecDeque<Instant>,
+    paused_until: Option<Instant>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    /// 創建新的限制器
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    /// 等待直到可以發送下一個請求，並將其計入窗口
+    pub async fn acquire(&self) {
+        loop {
+            let resume_at = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                while state.sent.front().is_some_and(|sent| now.duration_since(*sent) >= REQUEST_WINDOW) {
+                    state.sent.pop_front();
+                }
+
+                match (state.paused_until.filter(|until| *until > now), state.sent.front()) {
+                    (Some(until), _) => {
+                        debug!("Cloudflare API requests paused, waiting {} seconds", until.duration_since(now).as_secs());
+                        until
+                    }
+                    (None, Some(oldest)) if state.sent.len() >= REQUEST_LIMIT => {
+                        let until = *oldest + REQUEST_WINDOW;
+                        info!("Cloudflare API request budget exhausted, waiting {} seconds", until.duration_since(now).as_secs());
+                        until
+                    }
+                    _ => {
+                        state.paused_until = None;
+                        state.sent.push_back(now);
+                        return;
+                    }
+                }
+            };
+
+            sleep_until(resume_at).await;
+        }
+    }
+
+    /// 暫停所有請求
+    ///
+    /// # 參數
+    ///
+    /// - `delay`: 暫停時間，已有更晚的暫停時保留較晚者
+    pub fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.paused_until.is_none_or(|paused_until| paused_until < until) {
+            state.paused_until = Some(until);
+        }
+    }
+}
+
+/// 取得帳號共用的限制器
+///
+/// # 參數
+///
+/// - `account`: 識別帳號的值（API 令牌或 Global API Key 的電子郵件）
+pub fn for_account(account: &str) -> Arc<RateLimiter> {
+    let limiters = LIMITERS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut limiters = limiters.lock().unwrap_or_else(|e| e.into_inner());
+
+    limiters.entry(account.to_string()).or_default().clone()
+}
+
+/// 讀取 429 響應的 `Retry-After` 標頭
+///
+/// 支援秒數與 HTTP 日期（IMF-fixdate）格式，已過去的日期視為立即重試；
+/// 標頭不存在或無法解析時使用 `DEFAULT_RETRY_AFTER`
+///
+/// # 參數
+///
+/// - `headers`: 響應標頭
+pub fn retry_after(headers: &HeaderMap) -> Duration {
+    retry_after_at(headers, SystemTime::now())
+}
+
+/// 以指定的目前時間讀取 `Retry-After` 標頭
+///
+/// # 參數
+///
+/// - `headers`: 響應標頭
+/// - `now`: 計算 HTTP 日期剩餘時間所用的目前時間
+fn retry_after_at(headers: &HeaderMap, now: SystemTime) -> Duration {
+    let Some(header) = headers.get(RETRY_AFTER) else {
+        return DEFAULT_RETRY_AFTER;
+    };
+
+    let delay = header.to_str().ok().map(str::trim).and_then(|value| match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(now).unwrap_or_default()),
+    });
+
+    delay.unwrap_or_else(|| {
+        warn!("Unsupported Retry-After header {:?}, waiting {} seconds", header, DEFAULT_RETRY_AFTER.as_secs());
+        DEFAULT_RETRY_AFTER
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_blocks_until_retry_after() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        limiter.pause(Duration::from_secs(30));
+        // 較短的暫停不會縮短已有的暫停
+        limiter.pause(Duration::from_secs(10));
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn enforces_request_budget() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..REQUEST_LIMIT / 2 {
+            limiter.acquire().await;
+        }
+        tokio::time::advance(Duration::from_secs(100)).await;
+        for _ in 0..REQUEST_LIMIT / 2 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::from_secs(100));
+
+        // 窗口已滿，等到最早的請求離開窗口
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), REQUEST_WINDOW);
+
+        // 最早的一半離開窗口後可以再發送 599 個請求
+        for _ in 1..REQUEST_LIMIT / 2 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), REQUEST_WINDOW);
+
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), REQUEST_WINDOW + Duration::from_secs(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn accounts_share_limiters() {
+        let limiter = for_account("rate-limit-test-account");
+        limiter.pause(Duration::from_secs(5));
+
+        let start = Instant::now();
+        for_account("rate-limit-test-account").acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+
+        for_account("rate-limit-test-other-account").acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), DEFAULT_RETRY_AFTER);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(" 120 "));
+        assert_eq!(retry_after(&headers), Duration::from_secs(120));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("-1"));
+        assert_eq!(retry_after(&headers), DEFAULT_RETRY_AFTER);
+        assert_eq!(DEFAULT_RETRY_AFTER, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn parses_retry_after_http_date() {
+        let mut headers = HeaderMap::new();
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:30:30 GMT"));
+        assert_eq!(retry_after_at(&headers, now), Duration::from_secs(150));
+
+        // 已過去的日期立即重試
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:00:00 GMT"));
+        assert_eq!(retry_after_at(&headers, now), Duration::ZERO);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("21 Oct 2015 07:30:30"));
+        assert_eq!(retry_after_at(&headers, now), DEFAULT_RETRY_AFTER);
+    }
+}
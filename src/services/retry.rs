@@ -0,0 +1,273 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+
+/// 錯誤類別，決定重試的等待方式
+///
+/// # 變體
+///
+/// - `Transient`: 暫時性錯誤（網路、逾時、服務暫時無法使用），以指數退避快速重試
+/// - `Persistent`: 持續性錯誤（找不到記錄、非公網地址等），重試也不會很快恢復，以較長的固定間隔重試
+/// - `Fatal`: 無法重試的錯誤（配置錯誤、認證失敗、請求內容被拒絕），必須修改配置後重新啟動
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    Transient,
+    Persistent,
+    Fatal,
+}
+
+impl ErrorClass {
+    /// 判斷錯誤的類別
+    ///
+    /// # 參數
+    ///
+    /// - `error`: 錯誤
+    pub fn of(error: &AppError) -> Self {
+        match error {
+            AppError::ConfigError(_) | AppError::AuthenticationError(_) | AppError::ValidationError(_) => ErrorClass::Fatal,
+            AppError::NonPublicAddress(_) | AppError::NotFound(_) => ErrorClass::Persistent,
+            AppError::ExternalServiceError(_)
+            | AppError::InvalidIpResponse(_)
+            | AppError::AddressFamilyUnavailable(_)
+            | AppError::RateLimited(_)
+            | AppError::InternalError(_) => ErrorClass::Transient,
+        }
+    }
+
+    /// 錯誤是否無法重試
+    ///
+    /// # 參數
+    ///
+    /// - `error`: 錯誤
+    pub fn is_fatal(error: &AppError) -> bool {
+        Self::of(error) == ErrorClass::Fatal
+    }
+}
+
+/// 重試策略
+///
+/// 暫時性錯誤從 `initial_delay` 開始，每次失敗乘以 `multiplier`，最長 `max_delay`；
+/// 連續失敗超過 `max_retries` 次後視為持續性錯誤。持續性錯誤固定等待 `persistent_delay`。
+/// 所有等待時間都加上 ±`jitter` 比例的隨機偏移，避免多個服務同時重試
+///
+/// # 欄位
+///
+/// - `initial_delay`: 第一次重試前的等待時間（秒）
+/// - `max_delay`: 指數退避的上限（秒）
+/// - `multiplier`: 每次失敗後等待時間的倍數
+/// - `jitter`: 隨機偏移的比例（0 到 1）
+/// - `max_retries`: 以指數退避重試的次數上限
+/// - `persistent_delay`: 持續性錯誤的等待時間（秒）
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub initial_delay: u64,
+    pub max_delay: u64,
+    pub multiplier: f64,
+    pub jitter: f64,
+    pub max_retries: u32,
+    pub persistent_delay: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: 5,
+            max_delay: 600,
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_retries: 10,
+            persistent_delay: 1800,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 驗證策略
+    ///
+    /// # 返回
+    ///
+    /// - `Result<(), String>`: 無效時返回錯誤描述
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_delay == 0 {
+            return Err("retry initial_delay must be at least 1 second".to_string());
+        }
+        if self.max_delay < self.initial_delay {
+            return Err("retry max_delay cannot be less than initial_delay".to_string());
+        }
+        if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            return Err("retry multiplier must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err("retry jitter must be between 0 and 1".to_string());
+        }
+        if self.persistent_delay == 0 {
+            return Err("retry persistent_delay must be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 依重試策略計算連續失敗的等待時間
+///
+/// # 欄位
+///
+/// - `policy`: 重試策略
+/// - `failures`: 目前連續失敗的次數
+pub struct Backoff {
+    policy: RetryPolicy,
+    failures: u32,
+}
+
+impl Backoff {
+    /// 創建新的退避計算器
+    ///
+    /// # 參數
+    ///
+    /// - `policy`: 重試策略
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy, failures: 0 }
+    }
+
+    /// 記錄一次失敗並返回下次重試前的等待時間
+    ///
+    /// # 參數
+    ///
+    /// - `error`: 本次失敗的錯誤
+    pub fn next_delay(&mut self, error: &AppError) -> Duration {
+        self.failures = self.failures.saturating_add(1);
+
+        let exhausted = self.failures > self.policy.max_retries;
+        let seconds = match ErrorClass::of(error) {
+            ErrorClass::Transient if !exhausted => {
+                let exponent = i32::try_from(self.failures - 1).unwrap_or(i32::MAX);
+                (self.policy.initial_delay as f64 * self.policy.multiplier.powi(exponent))
+                    .min(self.policy.max_delay as f64)
+            }
+            _ => self.policy.persistent_delay as f64,
+        };
+
+        let jitter = seconds * self.policy.jitter;
+        let seconds = if jitter > 0.0 {
+            rand::random_range(seconds - jitter..=seconds + jitter)
+        } else {
+            seconds
+        };
+
+        Duration::from_secs_f64(seconds.max(1.0))
+    }
+
+    /// 成功後重置連續失敗次數
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    /// 目前連續失敗的次數
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 沒有隨機偏移的策略
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_delay: 5,
+            max_delay: 60,
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_retries: 6,
+            persistent_delay: 1800,
+        }
+    }
+
+    fn transient() -> AppError {
+        AppError::ExternalServiceError("timeout".to_string())
+    }
+
+    #[test]
+    fn grows_exponentially_up_to_max_delay() {
+        let mut backoff = Backoff::new(policy());
+        let delays: Vec<u64> = (0..6).map(|_| backoff.next_delay(&transient()).as_secs()).collect();
+        assert_eq!(delays, [5, 10, 20, 40, 60, 60]);
+        assert_eq!(backoff.failures(), 6);
+    }
+
+    #[test]
+    fn switches_to_persistent_delay_after_max_retries() {
+        let mut backoff = Backoff::new(policy());
+        for _ in 0..6 {
+            backoff.next_delay(&transient());
+        }
+        assert_eq!(backoff.next_delay(&transient()), Duration::from_secs(1800));
+        assert_eq!(backoff.next_delay(&transient()), Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn persistent_errors_wait_persistent_delay() {
+        let mut backoff = Backoff::new(policy());
+        for error in [
+            AppError::NotFound("record".to_string()),
+            AppError::NonPublicAddress("100.64.0.1".to_string()),
+        ] {
+            assert_eq!(ErrorClass::of(&error), ErrorClass::Persistent);
+            assert_eq!(backoff.next_delay(&error), Duration::from_secs(1800));
+        }
+
+        // 之後的暫時性錯誤仍依失敗次數退避
+        assert_eq!(backoff.next_delay(&transient()), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn classifies_fatal_errors() {
+        assert!(ErrorClass::is_fatal(&AppError::AuthenticationError("token".to_string())));
+        assert!(ErrorClass::is_fatal(&AppError::ValidationError("ttl".to_string())));
+        assert!(ErrorClass::is_fatal(&AppError::ConfigError("Invalid API token".to_string())));
+        assert!(!ErrorClass::is_fatal(&AppError::RateLimited("slow down".to_string())));
+        assert_eq!(ErrorClass::of(&AppError::RateLimited("slow down".to_string())), ErrorClass::Transient);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy { jitter: 0.2, ..policy() };
+        for failures in 1..=4u32 {
+            let base = 5.0 * 2f64.powi(failures as i32 - 1);
+            for _ in 0..100 {
+                let mut backoff = Backoff { policy: policy.clone(), failures: failures - 1 };
+                let delay = backoff.next_delay(&transient()).as_secs_f64();
+                assert!((base * 0.8 - 1e-6..=base * 1.2 + 1e-6).contains(&delay), "failure {}: {}", failures, delay);
+            }
+        }
+
+        // 偏移後不少於 1 秒
+        let policy = RetryPolicy { initial_delay: 1, jitter: 1.0, ..policy };
+        for _ in 0..100 {
+            assert!(Backoff::new(policy.clone()).next_delay(&transient()) >= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn reset_restarts_backoff() {
+        let mut backoff = Backoff::new(policy());
+        for _ in 0..8 {
+            backoff.next_delay(&transient());
+        }
+        backoff.reset();
+        assert_eq!(backoff.failures(), 0);
+        assert_eq!(backoff.next_delay(&transient()), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn validates_policy() {
+        assert!(RetryPolicy::default().validate().is_ok());
+        assert!(RetryPolicy { initial_delay: 0, ..policy() }.validate().is_err());
+        assert!(RetryPolicy { max_delay: 1, ..policy() }.validate().is_err());
+        assert!(RetryPolicy { multiplier: 0.5, ..policy() }.validate().is_err());
+        assert!(RetryPolicy { multiplier: f64::NAN, ..policy() }.validate().is_err());
+        assert!(RetryPolicy { jitter: 1.5, ..policy() }.validate().is_err());
+        assert!(RetryPolicy { persistent_delay: 0, ..policy() }.validate().is_err());
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use log::{debug, warn};
+
+/// 狀態文件名稱
+const STATE_FILE: &str = "state.json";
+
+/// 只有時間戳變更時，寫回文件的最短間隔
+const TIMESTAMP_WRITE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 單筆記錄的持久化狀態
+///
+/// 時間皆為 Unix 時間戳（秒）
+///
+/// # 欄位
+///
+/// - `last_observed_ip`: 最後一次偵測到的地址
+/// - `last_observed_at`: 最後一次偵測到地址的時間
+/// - `published_ip`: 最後一次成功寫入（或確認）記錄的地址，記錄被刪除後清除
+/// - `last_updated_at`: 最後一次成功更新（或刪除）的時間
+/// - `last_error`: 最後一次錯誤訊息，成功更新後清除
+/// - `last_error_at`: 最後一次錯誤的時間
+/// - `failed`: 記錄因無法重試的錯誤而停止更新，成功更新後清除
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecordState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_observed_ip: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_observed_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_ip: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_updated_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub failed: bool,
+}
+
+/// DDNS 狀態存儲
+///
+/// 以 JSON 文件保存每筆記錄的狀態，讓服務重新啟動後不必重新寫入未變更的記錄。
+/// 變更先保存在記憶體中，由 [`StateStore::flush`] 在每次檢查結束時一次寫回文件
+/// （先寫入暫存文件再改名，避免中途當機留下損壞的文件）。只有時間戳變更時最多每
+/// `TIMESTAMP_WRITE_INTERVAL` 寫入一次
+///
+/// # 欄位
+///
+/// - `path`: 狀態文件路徑
+/// - `records`: 以記錄鍵索引的狀態與尚未寫入的變更
+/// - `writing`: 確保同時只有一個寫入，且較新的內容不會被較舊的覆蓋
+pub struct StateStore {
+    path: PathBuf,
+    records: Mutex<Records>,
+    writing: tokio::sync::Mutex<()>,
+}
+
+/// 記憶體中的狀態
+///
+/// # 欄位
+///
+/// - `states`: 以記錄鍵索引的狀態
+/// - `changed`: 有時間戳以外的變更尚未寫入
+/// - `touched`: 有時間戳變更尚未寫入
+/// - `written_at`: 最後一次寫入（或載入）文件的時間
+struct Records {
+    states: HashMap<String, RecordState>,
+    changed: bool,
+    touched: bool,
+    written_at: Instant,
+}
+
+impl Records {
+    fn new(states: HashMap<String, RecordState>) -> Self {
+        Self {
+            states,
+            changed: false,
+            touched: false,
+            written_at: Instant::now(),
+        }
+    }
+
+    /// 是否需要寫回文件
+    fn due(&self) -> bool {
+        self.changed || (self.touched && self.written_at.elapsed() >= TIMESTAMP_WRITE_INTERVAL)
+    }
+}
+
+impl StateStore {
+    /// 從資料目錄載入狀態
+    ///
+    /// # 參數
+    ///
+    /// - `data_dir`: 資料目錄，不存在時會在第一次寫入時建立
+    ///
+    /// # 返回
+    ///
+    /// - `Result<Self, AppError>`: 成功時返回狀態存儲，文件不存在時為空的狀態；文件無法讀取或解析時返回錯誤
+    pub fn load(data_dir: &Path) -> Result<Self, AppError> {
+        let path = data_dir.join(STATE_FILE);
+        let records = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| AppError::ConfigError(format!("Failed to parse state file {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(AppError::InternalError(format!("Failed to read state file {}: {}", path.display(), e))),
+        };
+
+        Ok(Self {
+            path,
+            records: Mutex::new(Records::new(records)),
+            writing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// 建立不載入任何內容的狀態存儲
+    ///
+    /// 用於現有狀態文件損壞時重新開始，第一次寫入時會覆蓋該文件
+    ///
+    /// # 參數
+    ///
+    /// - `data_dir`: 資料目錄
+    pub fn empty(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(STATE_FILE),
+            records: Mutex::new(Records::new(HashMap::new())),
+            writing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 狀態文件路徑
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 已保存狀態的記錄數
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).states.len()
+    }
+
+    /// 是否沒有任何已保存的狀態
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 取得記錄的狀態
+    ///
+    /// # 參數
+    ///
+    /// - `key`: 記錄鍵
+    pub fn get(&self, key: &str) -> Option<RecordState> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).states.get(key).cloned()
+    }
+
+    /// 保存偵測到的地址
+    ///
+    /// # 參數
+    ///
+    /// - `key`: 記錄鍵
+    /// - `ip`: 偵測到的地址
+    pub fn record_observed(&self, key: &str, ip: IpAddr) {
+        self.modify(key, |state| {
+            state.last_observed_ip = Some(ip);
+            state.last_observed_at = Some(now());
+        });
+    }
+
+    /// 保存成功的更新，並清除先前的錯誤
+    ///
+    /// # 參數
+    ///
+    /// - `key`: 記錄鍵
+    /// - `ip`: 記錄目前指向的地址
+    pub fn record_success(&self, key: &str, ip: IpAddr) {
+        self.modify(key, |state| {
+            state.published_ip = Some(ip);
+            state.last_updated_at = Some(now());
+            state.last_error = None;
+            state.last_error_at = None;
+            state.failed = false;
+        });
+    }
+
+    /// 保存記錄已被刪除
+    ///
+    /// # 參數
+    ///
+    /// - `key`: 記錄鍵
+    pub fn record_removed(&self, key: &str) {
+        self.modify(key, |state| {
+            state.published_ip = None;
+            state.last_updated_at = Some(now());
+            state.last_error = None;
+            state.last_error_at = None;
+            state.failed = false;
+        });
+    }
+
+    /// 保存失敗的檢查或更新
+    ///
+    /// # 參數
+    ///
+    /// - `key`: 記錄鍵
+    /// - `error`: 錯誤
+    pub fn record_error(&self, key: &str, error: &AppError) {
+        self.modify(key, |state| {
+            state.last_error = Some(error.to_string());
+            state.last_error_at = Some(now());
+        });
+    }
+
+    /// 保存無法重試的錯誤，將記錄標記為失敗
+    ///
+    /// # 參數
+    ///
+    /// - `key`: 記錄鍵
+    /// - `error`: 錯誤
+    pub fn record_failed(&self, key: &str, error: &AppError) {
+        self.modify(key, |state| {
+            state.last_error = Some(error.to_string());
+            state.last_error_at = Some(now());
+            state.failed = true;
+        });
+    }
+
+    /// 將尚未寫入的變更寫回文件
+    ///
+    /// 沒有變更，或只有時間戳變更且距離上次寫入未滿 `TIMESTAMP_WRITE_INTERVAL` 時不寫入。
+    /// 文件在阻塞執行緒池中寫入；寫入失敗不影響 DDNS 更新，只是重新啟動後無法沿用狀態，下次呼叫時重試
+    pub async fn flush(&self) {
+        let _writing = self.writing.lock().await;
+        let content = {
+            let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+            if !records.due() {
+                return;
+            }
+            let content = match serde_json::to_string_pretty(&records.states) {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("Failed to serialize state: {}", e);
+                    return;
+                }
+            };
+            records.changed = false;
+            records.touched = false;
+            records.written_at = Instant::now();
+            content
+        };
+
+        let path = self.path.clone();
+        let result = tokio::task::spawn_blocking(move || persist(&path, &content))
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e)));
+        match result {
+            Ok(()) => debug!("State written to {}", self.path.display()),
+            Err(e) => {
+                warn!("Failed to write state file {}: {}", self.path.display(), e);
+                self.records.lock().unwrap_or_else(|e| e.into_inner()).changed = true;
+            }
+        }
+    }
+
+    /// 修改記錄的狀態，標記為需要寫回文件
+    fn modify(&self, key: &str, update: impl FnOnce(&mut RecordState)) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let state = records.states.entry(key.to_string()).or_default();
+        let before = state.clone();
+        update(state);
+
+        if state.without_timestamps() != before.without_timestamps() {
+            records.changed = true;
+        } else {
+            records.touched = true;
+        }
+    }
+}
+
+impl RecordState {
+    /// 時間戳以外的欄位，用於判斷變更是否需要立即寫入
+    fn without_timestamps(&self) -> (Option<IpAddr>, Option<IpAddr>, Option<&str>, bool) {
+        (self.last_observed_ip, self.published_ip, self.last_error.as_deref(), self.failed)
+    }
+}
+
+/// 將狀態寫入文件
+///
+/// # 參數
+///
+/// - `path`: 狀態文件路徑
+/// - `content`: 序列化後的狀態
+fn persist(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)
+}
+
+/// 目前的 Unix 時間戳（秒）
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每個測試使用獨立的暫存資料目錄
+    fn data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cloudflare-ddns-state-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn changes_are_written_on_flush() {
+        let dir = data_dir("flush");
+        let store = StateStore::load(&dir).unwrap();
+
+        store.record_observed("zone/ipv4/a", ip("198.51.100.7"));
+        store.record_success("zone/ipv4/a", ip("198.51.100.7"));
+        store.record_observed("zone/ipv4/b", ip("198.51.100.7"));
+        assert!(!store.path().exists());
+
+        store.flush().await;
+        let loaded = StateStore::load(&dir).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get("zone/ipv4/a").unwrap().published_ip, Some(ip("198.51.100.7")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn timestamp_only_changes_are_not_written() {
+        let dir = data_dir("timestamps");
+        let store = StateStore::load(&dir).unwrap();
+        store.record_observed("zone/ipv4/a", ip("198.51.100.7"));
+        store.record_success("zone/ipv4/a", ip("198.51.100.7"));
+        store.flush().await;
+        fs::remove_file(store.path()).unwrap();
+
+        store.record_observed("zone/ipv4/a", ip("198.51.100.7"));
+        store.flush().await;
+        store.record_success("zone/ipv4/a", ip("198.51.100.7"));
+        store.flush().await;
+        assert!(!store.path().exists());
+
+        // 其他欄位變更時一併寫入時間戳
+        store.record_observed("zone/ipv4/a", ip("198.51.100.8"));
+        store.flush().await;
+        assert_eq!(StateStore::load(&dir).unwrap().get("zone/ipv4/a").unwrap().last_observed_ip, Some(ip("198.51.100.8")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn repeated_errors_are_written_once() {
+        let dir = data_dir("errors");
+        let store = StateStore::load(&dir).unwrap();
+        let error = AppError::ExternalServiceError("timeout".to_string());
+
+        store.record_error("zone/ipv4/a", &error);
+        store.flush().await;
+        fs::remove_file(store.path()).unwrap();
+
+        store.record_error("zone/ipv4/a", &error);
+        store.flush().await;
+        assert!(!store.path().exists());
+
+        store.record_failed("zone/ipv4/a", &error);
+        store.flush().await;
+        assert!(StateStore::load(&dir).unwrap().get("zone/ipv4/a").unwrap().failed);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
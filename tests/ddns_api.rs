@@ -0,0 +1,63 @@
+use actix_web::{test, web, App, HttpResponse, HttpServer};
+use cloudflare_ddns::api;
+use cloudflare_ddns::mock::{self, MockCloudflare};
+
+/// 測試用的 API 令牌
+const TOKEN: &str = "test-token";
+
+/// 在背景啟動回傳固定 IPv4 地址的 IP 來源
+///
+/// # 返回
+///
+/// - IP 來源的 URL
+fn start_ip_source(ip: &'static str) -> String {
+    let server = HttpServer::new(move || App::new().route("/", web::get().to(move || async move { HttpResponse::Ok().body(ip) })))
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let address = server.addrs()[0];
+    tokio::spawn(server.run());
+    format!("http://{}/", address)
+}
+
+/// `/ddns` 端點從環境變量載入配置，因此整個流程放在同一個測試中，避免與其他測試共用環境變量
+#[actix_web::test]
+async fn ddns_endpoints_update_mock_cloudflare() {
+    let cloudflare = web::Data::new(MockCloudflare::new(Some(TOKEN.to_string())));
+    let (handle, address) = mock::start(cloudflare.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+    let id = cloudflare.insert("zone1", "home.example.com", "A", "192.0.2.1");
+
+    // SAFETY: 這個測試執行檔只有一個測試，設置環境變量時沒有其他執行緒讀取它們
+    unsafe {
+        std::env::set_var("CLOUDFLARE_API_BASE_URL", format!("http://{}/client/v4", address));
+        std::env::set_var("CLOUDFLARE_API_TOKEN", TOKEN);
+        std::env::set_var("CLOUDFLARE_ZONE_ID", "zone1");
+        std::env::set_var("CLOUDFLARE_RECORD_NAME", "home.example.com");
+        std::env::set_var("DDNS_IP_SOURCES", start_ip_source("198.51.100.7"));
+        std::env::set_var("DDNS_NON_PUBLIC_POLICY", "warn");
+    }
+
+    let app = test::init_service(App::new().configure(api::configure_routes)).await;
+
+    let response = test::call_service(&app, test::TestRequest::get().uri("/api/v1/ddns/update/ipv4").to_request()).await;
+    assert!(response.status().is_success(), "{:?}", response.status());
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["message"], "ipv4 DNS record updated");
+    assert_eq!(cloudflare.get(&id).unwrap().content, "198.51.100.7");
+
+    // 向下兼容端點，記錄已是最新時不再寫入
+    let response = test::call_service(&app, test::TestRequest::get().uri("/api/v1/ddns/update").to_request()).await;
+    assert!(response.status().is_success(), "{:?}", response.status());
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["message"], "ipv4 DNS record already up to date");
+    assert_eq!(cloudflare.requests().iter().filter(|request| request.starts_with("PATCH ")).count(), 1);
+
+    // Cloudflare 拒絕令牌時返回 502
+    unsafe {
+        std::env::set_var("CLOUDFLARE_API_TOKEN", "wrong-token");
+    }
+    let response = test::call_service(&app, test::TestRequest::get().uri("/api/v1/ddns/update/ipv4").to_request()).await;
+    assert_eq!(response.status(), actix_web::http::StatusCode::BAD_GATEWAY);
+
+    handle.stop(false).await;
+}
@@ -0,0 +1,322 @@
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use cloudflare_ddns::error::AppError;
+use cloudflare_ddns::mock::{self, MockCloudflare};
+use cloudflare_ddns::services::ddns::{DdnsConfig, DdnsService};
+use cloudflare_ddns::services::state::StateStore;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 測試用的 API 令牌
+const TOKEN: &str = "test-token";
+
+/// 測試用的區域 ID
+const ZONE: &str = "zone1";
+
+/// 在背景啟動只有 `ZONE` 區域的模擬 Cloudflare API
+///
+/// # 返回
+///
+/// - 模擬 API 的狀態、用於停止伺服器的控制代碼與 API 基礎 URL
+fn start_mock() -> (web::Data<MockCloudflare>, ServerHandle, String) {
+    let mock = web::Data::new(MockCloudflare::new(Some(TOKEN.to_string())));
+    mock.add_zone(ZONE, "example.com");
+    let (handle, address) = mock::start(mock.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+    (mock, handle, format!("http://{}/client/v4", address))
+}
+
+/// 建立指向模擬 API 的 IPv4 配置
+///
+/// # 參數
+///
+/// - `base_url`: 模擬 API 的基礎 URL
+/// - `overrides`: 覆蓋預設值的欄位
+fn config(base_url: &str, overrides: serde_json::Value) -> DdnsConfig {
+    let mut config = serde_json::json!({
+        "api_token": TOKEN,
+        "zone_id": ZONE,
+        "api_base_url": base_url,
+        "record_name": "home.example.com",
+        "update_interval": 300,
+        "ip_type": "ipv4"
+    });
+    for (key, value) in overrides.as_object().unwrap() {
+        config[key] = value.clone();
+    }
+    serde_json::from_value(config).unwrap()
+}
+
+/// 在背景啟動回傳固定地址的 IP 來源
+///
+/// # 返回
+///
+/// - IP 來源的 URL
+fn start_ip_source(ip: &'static str) -> String {
+    let server = HttpServer::new(move || App::new().route("/", web::get().to(move || async move { HttpResponse::Ok().body(ip) })))
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let address = server.addrs()[0];
+    tokio::spawn(server.run());
+    format!("http://{}/", address)
+}
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+/// 寫入請求（建立、取代、部分更新、刪除）
+fn writes(mock: &MockCloudflare) -> Vec<String> {
+    mock.requests()
+        .into_iter()
+        .filter(|request| !request.starts_with("GET "))
+        .collect()
+}
+
+#[tokio::test]
+async fn updates_record_looked_up_by_name() {
+    let (mock, handle, base_url) = start_mock();
+    let id = mock.insert(ZONE, "home.example.com", "A", "192.0.2.1");
+    mock.insert(ZONE, "home.example.com", "AAAA", "2001:db8::1");
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({}))).unwrap();
+    let result = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap();
+
+    assert_eq!(result["message"], "ipv4 DNS record updated");
+    assert_eq!(mock.get(&id).unwrap().content, "198.51.100.7");
+    assert!(mock.requests().iter().any(|request| request.starts_with("GET ") && request.ends_with("/dns_records")));
+    assert_eq!(writes(&mock), vec![format!("PATCH /client/v4/zones/{}/dns_records/{}", ZONE, id)]);
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn creates_missing_record_when_enabled() {
+    let (mock, handle, base_url) = start_mock();
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({ "create_if_missing": true, "proxied": true }))).unwrap();
+    let result = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap();
+
+    assert_eq!(result["message"], "ipv4 DNS record created");
+    let records = mock.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name, "home.example.com");
+    assert_eq!(records[0].record_type, "A");
+    assert_eq!(records[0].content, "198.51.100.7");
+    assert!(records[0].proxied);
+
+    // 建立後快取記錄 ID，下一次更新直接讀取該記錄
+    service.update_record_with_ip(ip("198.51.100.8")).await.unwrap();
+    assert_eq!(mock.get(&records[0].id).unwrap().content, "198.51.100.8");
+    assert_eq!(mock.records().len(), 1);
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn missing_record_without_create_is_not_found() {
+    let (mock, handle, base_url) = start_mock();
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({}))).unwrap();
+    let error = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap_err();
+
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+    assert!(writes(&mock).is_empty());
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn skips_write_when_record_is_up_to_date() {
+    let (mock, handle, base_url) = start_mock();
+    mock.insert(ZONE, "home.example.com", "A", "198.51.100.7");
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({}))).unwrap();
+    let result = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap();
+
+    assert_eq!(result["message"], "ipv4 DNS record already up to date");
+    assert!(writes(&mock).is_empty(), "{:?}", mock.requests());
+
+    // 設定不同時只發送不同的欄位
+    let service = DdnsService::new(config(&base_url, serde_json::json!({ "ttl": 300 }))).unwrap();
+    let result = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap();
+    assert_eq!(result["message"], "ipv4 DNS record updated");
+    assert_eq!(writes(&mock).len(), 1);
+    assert_eq!(mock.records()[0].ttl, 300);
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn rejected_token_is_authentication_error() {
+    let (mock, handle, base_url) = start_mock();
+    mock.insert(ZONE, "home.example.com", "A", "192.0.2.1");
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({ "api_token": "wrong-token" }))).unwrap();
+    let error = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap_err();
+
+    assert!(matches!(error, AppError::AuthenticationError(_)), "{:?}", error);
+    assert_eq!(mock.records()[0].content, "192.0.2.1");
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn global_api_key_is_accepted() {
+    let (mock, handle, base_url) = start_mock();
+    let id = mock.insert(ZONE, "home.example.com", "A", "192.0.2.1");
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({
+        "api_token": "",
+        "api_email": "owner@example.com",
+        "api_key": TOKEN
+    }))).unwrap();
+    service.update_record_with_ip(ip("198.51.100.7")).await.unwrap();
+
+    assert_eq!(mock.get(&id).unwrap().content, "198.51.100.7");
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn unknown_record_id_is_not_found() {
+    let (mock, handle, base_url) = start_mock();
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({ "record_id": "does-not-exist" }))).unwrap();
+    let error = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap_err();
+
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+    assert!(writes(&mock).is_empty());
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn reconcile_recreates_deleted_record_with_configured_id() {
+    let (mock, handle, base_url) = start_mock();
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({
+        "record_id": "deleted-record",
+        "create_if_missing": true
+    }))).unwrap();
+    assert!(service.reconcile(ip("198.51.100.7")).await.unwrap());
+
+    let records = mock.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name, "home.example.com");
+    assert_eq!(records[0].content, "198.51.100.7");
+
+    // 之後使用新建立的記錄 ID
+    assert!(!service.reconcile(ip("198.51.100.7")).await.unwrap());
+    assert_eq!(mock.records().len(), 1);
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn fatal_error_skips_only_the_failed_record() {
+    let (mock, handle, base_url) = start_mock();
+    let home = mock.insert(ZONE, "home.example.com", "A", "192.0.2.1");
+    // 將 vpn 更新為 198.51.100.7 會與已有的相同記錄衝突（81058）
+    let vpn = mock.insert(ZONE, "vpn.example.com", "A", "192.0.2.1");
+    mock.insert(ZONE, "vpn.example.com", "A", "198.51.100.7");
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({ "records": [{ "id": vpn }] }))).unwrap();
+    let result = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap();
+    assert_eq!(result["message"], "ipv4 DNS record updated");
+    assert_eq!(mock.get(&home).unwrap().content, "198.51.100.7");
+    assert_eq!(mock.get(&vpn).unwrap().content, "192.0.2.1");
+
+    // 之後的檢查略過失敗的記錄，其餘記錄繼續更新
+    let requests = mock.requests().len();
+    service.update_record_with_ip(ip("198.51.100.8")).await.unwrap();
+    assert_eq!(mock.get(&home).unwrap().content, "198.51.100.8");
+    assert!(mock.requests()[requests..].iter().all(|request| !request.ends_with(&vpn)), "{:?}", mock.requests());
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn unknown_zone_is_not_found() {
+    let (mock, handle, base_url) = start_mock();
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({ "zone_id": "other-zone", "create_if_missing": true }))).unwrap();
+    let error = service.update_record_with_ip(ip("198.51.100.7")).await.unwrap_err();
+
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+    assert!(mock.records().is_empty());
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn zone_endpoints_return_seeded_zones() {
+    let (_, handle, base_url) = start_mock();
+    let client = reqwest::Client::new();
+    let get = |path: &str| client.get(format!("{}{}", base_url, path)).bearer_auth(TOKEN).send();
+
+    let body: serde_json::Value = get("/zones?name=example.com").await.unwrap().json().await.unwrap();
+    assert_eq!(body["result"][0]["id"], ZONE);
+    assert_eq!(body["result_info"]["count"], 1);
+
+    let body: serde_json::Value = get(&format!("/zones/{}", ZONE)).await.unwrap().json().await.unwrap();
+    assert_eq!(body["result"]["name"], "example.com");
+    assert_eq!(body["result"]["status"], "active");
+
+    let response = get("/zones/other-zone").await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], 7003);
+    handle.stop(false).await;
+}
+
+#[test]
+fn missing_credentials_are_config_error() {
+    let config = config("http://127.0.0.1:1/client/v4", serde_json::json!({ "api_token": "" }));
+    assert!(matches!(DdnsService::new(config), Err(AppError::ConfigError(_))));
+}
+
+#[tokio::test]
+async fn removed_family_is_not_recreated_without_create_if_missing() {
+    let (mock, handle, base_url) = start_mock();
+    mock.insert(ZONE, "home.example.com", "A", "198.51.100.7");
+
+    let services = DdnsService::for_config(config(&base_url, serde_json::json!({
+        "ip_type": "both",
+        "remove_when_unavailable": true
+    }))).unwrap();
+    let error = services[1].update_record_with_ip(ip("2001:db8::7")).await.unwrap_err();
+
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+    assert!(writes(&mock).is_empty(), "{:?}", mock.requests());
+
+    let services = DdnsService::for_config(config(&base_url, serde_json::json!({
+        "ip_type": "both",
+        "remove_when_unavailable": true,
+        "create_if_missing": true
+    }))).unwrap();
+    services[1].update_record_with_ip(ip("2001:db8::7")).await.unwrap();
+    assert!(mock.records().iter().any(|record| record.record_type == "AAAA" && record.content == "2001:db8::7"));
+    handle.stop(false).await;
+}
+
+#[tokio::test]
+async fn first_check_after_restart_repairs_record_changed_while_stopped() {
+    let (mock, handle, base_url) = start_mock();
+    // 服務停止期間記錄在 Cloudflare 上被修改
+    let id = mock.insert(ZONE, "home.example.com", "A", "192.0.2.1");
+
+    let dir = std::env::temp_dir().join(format!("cloudflare-ddns-restart-{}", std::process::id()));
+    let state = StateStore::empty(&dir);
+    state.record_success(&format!("{}/ipv4/home.example.com", ZONE), ip("198.51.100.7"));
+
+    let service = DdnsService::new(config(&base_url, serde_json::json!({
+        "ip_sources": [start_ip_source("198.51.100.7")]
+    }))).unwrap().with_state(Arc::new(state));
+    let task = tokio::spawn(async move { service.start_auto_update().await });
+
+    let mut repaired = false;
+    for _ in 0..50 {
+        if mock.get(&id).unwrap().content == "198.51.100.7" {
+            repaired = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    task.abort();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(repaired, "{:?}", mock.requests());
+    handle.stop(false).await;
+}